@@ -0,0 +1,22 @@
+#![feature(test)]
+
+extern crate test;
+extern crate influent;
+
+use test::Bencher;
+use influent::query::{ResultParser, JsonParser};
+
+fn response(rows: usize) -> String {
+    let values: Vec<String> = (0..rows)
+        .map(|i| format!("[\"2015-06-11T20:46:02Z\",{},{}.5,\"server{}\",true]", i, i, i % 10))
+        .collect();
+
+    format!("{{\"results\":[{{\"statement_id\":0,\"series\":[{{\"name\":\"cpu\",\"columns\":[\"time\",\"count\",\"load\",\"host\",\"alive\"],\"values\":[{}]}}]}}]}}", values.join(","))
+}
+
+#[bench]
+fn bench_parse_json(b: &mut Bencher) {
+    let body = response(10000);
+
+    b.iter(|| JsonParser.parse(&body).unwrap().results.len());
+}
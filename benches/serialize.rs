@@ -0,0 +1,78 @@
+#![feature(test)]
+
+extern crate test;
+extern crate influent;
+
+use test::Bencher;
+use influent::serializer::Serializer;
+use influent::serializer::line::LineSerializer;
+use influent::serializer::cached::CachedMeasurement;
+use influent::measurement::{Measurement, Value};
+
+fn heartbeat<'a>() -> Measurement<'a> {
+    let mut measurement = Measurement::new("heartbeat");
+
+    measurement.add_tag("host", "server01");
+    measurement.add_tag("region", "us-west");
+    measurement.add_field("alive", Value::Boolean(true));
+    measurement.add_field("uptime", Value::Integer(1488));
+    measurement.add_field("message", Value::String("all systems, go"));
+
+    measurement
+}
+
+fn wide<'a>(names: &'a [String]) -> Measurement<'a> {
+    let mut measurement = Measurement::new("wide measurement");
+
+    for name in names {
+        measurement.add_tag(name, "some, value");
+        measurement.add_field(name, Value::Float(3.14));
+    }
+
+    measurement.set_timestamp(1434055562000000000);
+
+    measurement
+}
+
+#[bench]
+fn bench_serialize(b: &mut Bencher) {
+    let serializer = LineSerializer::new();
+    let mut measurement = heartbeat();
+    let mut timestamp = 1434055562000000000;
+
+    b.iter(|| {
+        timestamp += 1;
+        measurement.set_timestamp(timestamp);
+        serializer.serialize(&measurement)
+    });
+}
+
+#[bench]
+fn bench_serialize_cached(b: &mut Bencher) {
+    let serializer = LineSerializer::new();
+    let mut cached = CachedMeasurement::new(heartbeat());
+    let mut timestamp = 1434055562000000000;
+
+    b.iter(|| {
+        timestamp += 1;
+        cached.set_timestamp(timestamp);
+        cached.serialize(&serializer).len()
+    });
+}
+
+#[bench]
+fn bench_serialize_wide(b: &mut Bencher) {
+    let serializer = LineSerializer::new();
+    let names: Vec<String> = (0..50).map(|i| format!("key {}", i)).collect();
+    let measurement = wide(&names);
+
+    b.iter(|| serializer.serialize(&measurement));
+}
+
+#[bench]
+fn bench_serialize_many(b: &mut Bencher) {
+    let serializer = LineSerializer::new();
+    let measurements: Vec<Measurement> = (0..100).map(|_| heartbeat().with_timestamp(1434055562000000000)).collect();
+
+    b.iter(|| serializer.serialize_many(&measurements));
+}
@@ -0,0 +1,106 @@
+use std::cmp;
+use std::time::Duration;
+
+/// Strategy of delays between retries of the failed write.
+pub trait Backoff {
+    /// Returns delay before the retry `attempt`, which starts from 1, or `None` to give up.
+    fn next_delay(&self, attempt: u32) -> Option<Duration>;
+}
+
+/// Backoff, which doubles the delay on every retry, up to the `max` delay.
+pub struct ExponentialBackoff {
+    initial: Duration,
+    max: Duration,
+    retries: u32
+}
+
+impl ExponentialBackoff {
+    /// Constructs a new `ExponentialBackoff`, which gives up after `retries` retries.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use std::time::Duration;
+    /// use influent::client::backoff::{Backoff, ExponentialBackoff};
+    ///
+    /// let backoff = ExponentialBackoff::new(Duration::from_millis(100), Duration::from_secs(1), 5);
+    ///
+    /// assert_eq!(Some(Duration::from_millis(400)), backoff.next_delay(3));
+    /// assert_eq!(Some(Duration::from_secs(1)), backoff.next_delay(5));
+    /// assert_eq!(None, backoff.next_delay(6));
+    /// ```
+    pub fn new(initial: Duration, max: Duration, retries: u32) -> ExponentialBackoff {
+        ExponentialBackoff {
+            initial: initial,
+            max: max,
+            retries: retries
+        }
+    }
+}
+
+impl Backoff for ExponentialBackoff {
+    fn next_delay(&self, attempt: u32) -> Option<Duration> {
+        if attempt == 0 || attempt > self.retries {
+            return None;
+        }
+
+        let delay = 1u32.checked_shl(attempt - 1)
+            .and_then(|factor| self.initial.checked_mul(factor))
+            .unwrap_or(self.max);
+
+        Some(cmp::min(delay, self.max))
+    }
+}
+
+/// Backoff, which waits the same delay before every retry.
+pub struct ConstantBackoff {
+    delay: Duration,
+    retries: u32
+}
+
+impl ConstantBackoff {
+    /// Constructs a new `ConstantBackoff`, which gives up after `retries` retries.
+    pub fn new(delay: Duration, retries: u32) -> ConstantBackoff {
+        ConstantBackoff {
+            delay: delay,
+            retries: retries
+        }
+    }
+}
+
+impl Backoff for ConstantBackoff {
+    fn next_delay(&self, attempt: u32) -> Option<Duration> {
+        if attempt == 0 || attempt > self.retries {
+            return None;
+        }
+
+        Some(self.delay)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Backoff, ExponentialBackoff, ConstantBackoff};
+    use std::time::Duration;
+
+    #[test]
+    fn test_exponential_backoff() {
+        let backoff = ExponentialBackoff::new(Duration::from_millis(100), Duration::from_secs(1), 40);
+
+        assert_eq!(Some(Duration::from_millis(100)), backoff.next_delay(1));
+        assert_eq!(Some(Duration::from_millis(200)), backoff.next_delay(2));
+        assert_eq!(Some(Duration::from_millis(800)), backoff.next_delay(4));
+        assert_eq!(Some(Duration::from_secs(1)), backoff.next_delay(5));
+        assert_eq!(Some(Duration::from_secs(1)), backoff.next_delay(40));
+        assert_eq!(None, backoff.next_delay(41));
+    }
+
+    #[test]
+    fn test_constant_backoff() {
+        let backoff = ConstantBackoff::new(Duration::from_millis(100), 2);
+
+        assert_eq!(Some(Duration::from_millis(100)), backoff.next_delay(1));
+        assert_eq!(Some(Duration::from_millis(100)), backoff.next_delay(2));
+        assert_eq!(None, backoff.next_delay(3));
+    }
+}
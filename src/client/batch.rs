@@ -0,0 +1,210 @@
+use ::measurement::Measurement;
+use ::client::{Client, ClientError, ClientWriteResult, WriteReport};
+use std::collections::BTreeMap;
+
+/// Accumulates measurements and writes them with the underlying client at once.
+///
+/// Not yet written measurements are flushed when writer is dropped, unless it is disabled
+/// with `flush_on_drop(false)`. Use `close()` to flush explicitly.
+pub struct BatchWriter<'a, 'c, C: Client + 'c> {
+    client: &'c C,
+    measurements: Vec<Measurement<'a>>,
+    tags: BTreeMap<&'a str, &'a str>,
+    flush_on_drop: bool,
+    max_retained_capacity: Option<usize>
+}
+
+impl<'a, 'c, C: Client + 'c> BatchWriter<'a, 'c, C> {
+    /// Constructs a new `BatchWriter` over the client.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use influent::create_udp_client;
+    /// use influent::client::batch::BatchWriter;
+    /// use influent::measurement::{Measurement, Value};
+    ///
+    /// let client = create_udp_client(vec!["127.0.0.1:8089"]);
+    /// let mut writer = BatchWriter::new(&client);
+    ///
+    /// writer.add_tag("trace_id", "abc");
+    ///
+    /// let mut measurement = Measurement::new("key");
+    /// measurement.add_field("field", Value::Integer(1));
+    ///
+    /// writer.add(measurement);
+    /// writer.close().unwrap();
+    /// ```
+    pub fn new(client: &'c C) -> BatchWriter<'a, 'c, C> {
+        BatchWriter {
+            client: client,
+            measurements: vec![],
+            tags: BTreeMap::new(),
+            flush_on_drop: true,
+            max_retained_capacity: None
+        }
+    }
+
+    /// Sets capacity, in measurements, which the buffer of accumulated measurements is shrunk
+    /// back to after a successful flush, if it grew beyond it. By default the buffer is reused
+    /// as is, so a single huge batch leaves it permanently large.
+    ///
+    /// Only the measurements buffer is bounded: lines are serialized by the client on every
+    /// write, so the writer retains none of them.
+    pub fn set_max_retained_capacity(&mut self, capacity: Option<usize>) {
+        self.max_retained_capacity = capacity;
+    }
+
+    /// Sets whether not yet written measurements are flushed when writer is dropped.
+    /// Enabled by default.
+    pub fn flush_on_drop(&mut self, flush: bool) {
+        self.flush_on_drop = flush;
+    }
+
+    /// Adds session tag, which is set on every measurement added afterwards.
+    /// Tags of the measurement itself take precedence over session tags.
+    pub fn add_tag(&mut self, tag: &'a str, value: &'a str) {
+        self.tags.insert(tag, value);
+    }
+
+    /// Adds measurement to the batch.
+    pub fn add(&mut self, mut measurement: Measurement<'a>) {
+        for (tag, value) in self.tags.iter() {
+            measurement.add_tag_if_absent(tag, value);
+        }
+
+        self.measurements.push(measurement);
+    }
+
+    /// Returns count of not yet written measurements.
+    pub fn len(&self) -> usize {
+        self.measurements.len()
+    }
+
+    /// Writes accumulated measurements.
+    ///
+    /// On failure measurements which are not known to be persisted are kept for the next flush.
+    pub fn flush(&mut self) -> ClientWriteResult {
+        if self.measurements.is_empty() {
+            return Ok(WriteReport::empty());
+        }
+
+        match self.client.write_many(&self.measurements, None) {
+            Ok(report) => {
+                self.measurements.clear();
+
+                match self.max_retained_capacity {
+                    Some(capacity) if self.measurements.capacity() > capacity => {
+                        self.measurements = Vec::with_capacity(capacity);
+                    }
+                    _ => {}
+                }
+
+                Ok(report)
+            },
+            Err(ClientError::Interrupted(report, err)) => {
+                self.measurements.drain(..report.written);
+                Err(ClientError::Interrupted(report, err))
+            },
+            Err(err) => Err(err)
+        }
+    }
+
+    /// Flushes accumulated measurements and consumes the writer.
+    pub fn close(mut self) -> ClientWriteResult {
+        self.flush_on_drop = false;
+        self.flush()
+    }
+}
+
+impl<'a, 'c, C: Client + 'c> Drop for BatchWriter<'a, 'c, C> {
+    fn drop(&mut self) {
+        if !self.flush_on_drop {
+            return;
+        }
+
+        match self.flush() {
+            Err(err) => warn!("could not flush batch on drop: {:?}", err),
+            _ => {}
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use ::client::mock::MockClient;
+    use ::measurement::{Measurement, Value};
+    use super::BatchWriter;
+
+    fn measurement<'a>(key: &'a str) -> Measurement<'a> {
+        let mut measurement = Measurement::new(key);
+        measurement.add_field("value", Value::Integer(1));
+        measurement
+    }
+
+    #[test]
+    fn test_session_tags() {
+        let client = MockClient::new();
+
+        {
+            let mut writer = BatchWriter::new(&client);
+            writer.add_tag("trace", "abc");
+
+            writer.add(measurement("a"));
+
+            let mut explicit = measurement("b");
+            explicit.add_tag("trace", "own");
+            writer.add(explicit);
+
+            assert_eq!(2, writer.flush().unwrap().written);
+        }
+
+        {
+            let mut writer = BatchWriter::new(&client);
+            writer.add(measurement("c"));
+        }
+
+        assert_eq!(vec!["a,trace=abc value=1i", "b,trace=own value=1i", "c value=1i"], *client.lines.borrow());
+    }
+
+    #[test]
+    fn test_flush_on_drop_disabled() {
+        let client = MockClient::new();
+
+        {
+            let mut writer = BatchWriter::new(&client);
+            writer.flush_on_drop(false);
+            writer.add(measurement("a"));
+        }
+
+        assert!(client.lines.borrow().is_empty());
+
+        let mut writer = BatchWriter::new(&client);
+        writer.flush_on_drop(false);
+        writer.add(measurement("b"));
+
+        assert_eq!(1, writer.close().unwrap().written);
+        assert_eq!(vec!["b value=1i"], *client.lines.borrow());
+    }
+
+    #[test]
+    fn test_max_retained_capacity() {
+        let client = MockClient::new();
+        let mut writer = BatchWriter::new(&client);
+        writer.set_max_retained_capacity(Some(16));
+
+        for _ in 0..1000 {
+            writer.add(measurement("a"));
+        }
+        assert!(writer.measurements.capacity() >= 1000);
+
+        writer.flush().unwrap();
+        assert!(writer.measurements.capacity() <= 16);
+
+        for _ in 0..10 {
+            writer.add(measurement("b"));
+        }
+        writer.flush().unwrap();
+        assert!(writer.measurements.capacity() >= 10 && writer.measurements.capacity() <= 16);
+    }
+}
@@ -0,0 +1,157 @@
+use ::measurement::Measurement;
+use ::client::{Precision, Client, ClientError, ClientReadResult, ClientWriteResult, WriteReport};
+use std::collections::HashMap;
+use std::time::{Duration, Instant};
+use std::cell::RefCell;
+
+/// Client, which skips measurements with the same field values as the last written
+/// measurement of the series, e.g. for sensors reporting redundant identical readings.
+///
+/// Unchanged measurement is still written once `heartbeat` passed since the last write
+/// of the series, so the series does not look dead.
+pub struct DedupClient<C: Client> {
+    client: C,
+    heartbeat: Duration,
+    // series -> (field values, time of the last write)
+    last: RefCell<HashMap<String, (String, Instant)>>
+}
+
+impl<C: Client> DedupClient<C> {
+    /// Constructs a new `DedupClient` over the client.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use std::time::Duration;
+    /// use influent::create_udp_client;
+    /// use influent::client::dedup::DedupClient;
+    ///
+    /// let client = DedupClient::new(create_udp_client(vec!["127.0.0.1:8089"]), Duration::from_secs(60));
+    /// ```
+    pub fn new(client: C, heartbeat: Duration) -> DedupClient<C> {
+        DedupClient {
+            client: client,
+            heartbeat: heartbeat,
+            last: RefCell::new(HashMap::new())
+        }
+    }
+}
+
+fn series(measurement: &Measurement) -> String {
+    format!("{:?}{:?}", measurement.key, measurement.tags)
+}
+
+fn field_values(measurement: &Measurement) -> String {
+    format!("{:?}", measurement.fields)
+}
+
+impl<C: Client> Client for DedupClient<C> {
+    fn query(&self, q: String, epoch: Option<Precision>) -> ClientReadResult {
+        self.client.query(q, epoch)
+    }
+
+    fn write_one(&self, measurement: Measurement, precision: Option<Precision>) -> ClientWriteResult {
+        self.write_many(&[measurement], precision)
+    }
+
+    fn write_many(&self, measurements: &[Measurement], precision: Option<Precision>) -> ClientWriteResult {
+        let now = Instant::now();
+
+        // last writes are remembered only when the whole write succeeds,
+        // so after a failure duplicates are rather written again than lost
+        let mut pending = HashMap::new();
+        let mut changed = Vec::with_capacity(measurements.len());
+
+        {
+            let last = self.last.borrow();
+
+            for measurement in measurements {
+                let series = series(measurement);
+                let values = field_values(measurement);
+
+                let is_changed = match pending.get(&series).or_else(|| last.get(&series)) {
+                    Some(&(ref last_values, at)) => *last_values != values || now.duration_since(at) >= self.heartbeat,
+                    None => true
+                };
+
+                if is_changed {
+                    pending.insert(series, (values, now));
+                }
+                changed.push(is_changed);
+            }
+        }
+
+        let mut written = 0;
+        let mut start = 0;
+
+        // write runs of changed measurements, so the order is kept
+        while start < measurements.len() {
+            if !changed[start] {
+                start += 1;
+                continue;
+            }
+
+            let len = changed[start..].iter().take_while(|c| **c).count();
+
+            match self.client.write_many(&measurements[start..start + len], precision) {
+                Ok(_) => {
+                    written += len;
+                    start += len;
+                },
+                Err(ClientError::Interrupted(report, err)) => {
+                    let resume = start + report.written;
+                    return Err(ClientError::Interrupted(WriteReport { written: written + report.written, resume_index: Some(resume), points_written: None }, err));
+                },
+                Err(err) if start > 0 => {
+                    return Err(ClientError::Interrupted(WriteReport { written: written, resume_index: Some(start), points_written: None }, Box::new(err)));
+                },
+                Err(err) => return Err(err)
+            }
+        }
+
+        self.last.borrow_mut().extend(pending);
+
+        Ok(WriteReport { written: written, resume_index: None, points_written: None })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use ::client::Client;
+    use ::client::mock::MockClient;
+    use ::measurement::{Measurement, Value};
+    use super::DedupClient;
+    use std::time::Duration;
+    use std::thread;
+
+    fn reading<'a>(sensor: &'a str, value: i64) -> Measurement<'a> {
+        let mut measurement = Measurement::new("temperature");
+        measurement.add_tag("sensor", sensor);
+        measurement.add_field("value", Value::Integer(value));
+        measurement
+    }
+
+    #[test]
+    fn test_dedup() {
+        let client = DedupClient::new(MockClient::new(), Duration::from_millis(50));
+
+        let report = client.write_many(&[reading("a", 1), reading("a", 1), reading("b", 1), reading("a", 2)], None).unwrap();
+        assert_eq!(3, report.written);
+
+        client.write_one(reading("a", 2), None).unwrap();
+        client.write_one(reading("b", 1), None).unwrap();
+
+        thread::sleep(Duration::from_millis(60));
+
+        // heartbeat
+        client.write_one(reading("a", 2), None).unwrap();
+        client.write_one(reading("a", 2), None).unwrap();
+
+        assert_eq!(vec![
+            "temperature,sensor=a value=1i",
+            "temperature,sensor=b value=1i",
+            "temperature,sensor=a value=2i",
+            "temperature,sensor=a value=2i"
+        ], *client.client.lines.borrow());
+    }
+}
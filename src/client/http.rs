@@ -1,10 +1,28 @@
-use ::measurement::Measurement;
-use ::serializer::Serializer;
-use ::client::{Precision, Client, Credentials, ClientError, ClientReadResult, ClientWriteResult};
-use ::hurl::{Hurl, Request, Response, Method, Auth};
-use std::collections::HashMap;
+use ::measurement::{Measurement, Value};
+use ::serializer::{Serializer, SerializeError};
+use ::client::{Precision, Consistency, Client, Credentials, TokenCredentials, ClientError, ClientReadResult, ClientWriteResult, WriteReport, check_keys};
+use ::client::backoff::{Backoff, ConstantBackoff};
+use ::client::rate_limit::RateLimiter;
+use ::hurl::{Hurl, Request, Response, Method, Auth, TIMED_OUT};
+use ::query::{ResultParser, JsonParser, QueryValue};
+use ::escaping::{influxql_quote_ident, influxql_quote_literal};
+use rustc_serialize::json::Json;
+use std::collections::{BTreeMap, HashMap};
+use std::cell::Cell;
+use std::io::Write;
+use std::time::Duration;
+use std::thread;
+use std::slice;
 
 const MAX_BATCH: u16 = 5000;
+const ERROR_HEADER: &'static str = "X-Influxdb-Error";
+const POINTS_WRITTEN_HEADER: &'static str = "X-Influxdb-Points-Written";
+const VERSION_HEADER: &'static str = "X-Influxdb-Version";
+const SNIPPET_LEN: usize = 100;
+
+// heuristic window of plausible timestamps, between 1973 and 2286 years
+const MIN_PLAUSIBLE_SECONDS: i64 = 100000000;
+const MAX_PLAUSIBLE_SECONDS: i64 = 10000000000;
 
 pub enum WriteStatus {
     Success,
@@ -20,11 +38,49 @@ pub struct Options {
     pub chunk_size: Option<u16>
 }
 
+/// Where credentials are sent in the request.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum AuthMode {
+    /// `Authorization: Basic` header.
+    Header,
+    /// `u` and `p` query parameters, which may end up in logs of the server and proxies.
+    Query
+}
+
+/// Signs outgoing requests, e.g. for gateways requiring HMAC signatures.
+pub trait Signer {
+    /// Returns headers to attach to the request with given method, path and body.
+    fn sign(&self, method: &Method, path: &str, body: Option<&str>) -> Vec<(String, String)>;
+}
+
+/// Signer, which attaches nothing.
+pub struct NoopSigner;
+
+impl Signer for NoopSigner {
+    fn sign(&self, _: &Method, _: &str, _: Option<&str>) -> Vec<(String, String)> {
+        vec![]
+    }
+}
+
 pub struct HttpClient<'a> {
     credentials: Credentials<'a>,
     serializer: Box<Serializer>,
     hurl: Box<Hurl>,
+    signer: Box<Signer>,
+    result_parser: Box<ResultParser>,
+    backoff: Box<Backoff>,
+    rate_limiter: Option<RateLimiter>,
     hosts: Vec<&'a str>,
+    validate_timestamps: bool,
+    validate_measurements: bool,
+    nanoseconds_token: &'static str,
+    allowed_tag_values: HashMap<&'a str, Vec<&'a str>>,
+    retention_policy: Option<&'a str>,
+    consistency: Option<Consistency>,
+    auth_mode: AuthMode,
+    token: Option<TokenCredentials<'a>>,
+    org_header: Option<&'a str>,
+    next_host: Cell<usize>,
     pub max_batch: u16
 }
 
@@ -34,103 +90,843 @@ impl<'a> HttpClient<'a> {
             credentials: credentials,
             serializer: serializer,
             hurl: hurl,
+            signer: Box::new(NoopSigner),
+            result_parser: Box::new(JsonParser),
+            backoff: Box::new(ConstantBackoff::new(Duration::from_secs(0), 0)),
+            rate_limiter: None,
             hosts: vec![],
+            validate_timestamps: false,
+            validate_measurements: false,
+            nanoseconds_token: "n",
+            allowed_tag_values: HashMap::new(),
+            retention_policy: None,
+            consistency: None,
+            auth_mode: AuthMode::Header,
+            token: None,
+            org_header: None,
+            next_host: Cell::new(0),
             max_batch: MAX_BATCH
         }
     }
 
+    /// Constructs a client of InfluxDB 2.x. Writes are sent to `/api/v2/write` with `org` and
+    /// `bucket` parameters, and all requests are authorized with `Authorization: Token` header.
+    /// Queries still use `/query` of the 1.x compatibility API, with the bucket as database.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use influent::client::{TokenCredentials, Org};
+    /// use influent::client::http::HttpClient;
+    /// use influent::hurl::hyper::HyperHurl;
+    /// use influent::serializer::line::LineSerializer;
+    ///
+    /// let credentials = TokenCredentials { token: "secret", org: Org::Name("myorg"), bucket: "mybucket" };
+    /// let mut client = HttpClient::new_v2(credentials, Box::new(LineSerializer::new()), Box::new(HyperHurl::new()));
+    /// client.add_host("http://localhost:8086");
+    /// ```
+    pub fn new_v2(credentials: TokenCredentials<'a>, serializer: Box<Serializer>, hurl: Box<Hurl>) -> HttpClient<'a> {
+        let mut client = HttpClient::new(Credentials::anonymous(credentials.bucket), serializer, hurl);
+        client.token = Some(credentials);
+
+        client
+    }
+
+    /// Sets header, which carries the organization of InfluxDB 2.x writes in addition to
+    /// the query parameter, as some gateways of InfluxDB Cloud require. Not sent by default.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use influent::client::{TokenCredentials, Org};
+    /// use influent::client::http::HttpClient;
+    /// use influent::hurl::hyper::HyperHurl;
+    /// use influent::serializer::line::LineSerializer;
+    ///
+    /// let credentials = TokenCredentials { token: "secret", org: Org::Id("0123456789abcdef"), bucket: "mybucket" };
+    /// let mut client = HttpClient::new_v2(credentials, Box::new(LineSerializer::new()), Box::new(HyperHurl::new()));
+    /// client.set_org_header(Some("X-Influxdb-Org"));
+    /// ```
+    pub fn set_org_header(&mut self, header: Option<&'a str>) {
+        self.org_header = header;
+    }
+
+    /// Adds host of InfluxDB. Requests are spread over the hosts in rotation; on communication
+    /// or server error the next host is tried, so the error is returned only if all of them fail.
     pub fn add_host(&mut self, host: &'a str) {
         self.hosts.push(host);
     }
 
-    fn get_host(&self) -> &'a str {
-        match self.hosts.first() {
-            Some(host) => host,
-            None => panic!("Could not get host")
+    /// Sets whether timestamps of InfluxDB 2.x writes are checked to have plausible magnitude for
+    /// the write precision (nanoseconds, if not given), so e.g. seconds written as nanoseconds
+    /// are rejected before sending anything. Disabled by default, and never applied to 1.x
+    /// writes.
+    ///
+    /// The check is a heuristic: timestamps are plausible between 1973 and 2286 years, so
+    /// earlier points, e.g. of historical data, are rejected as well.
+    pub fn set_validate_timestamps(&mut self, validate: bool) {
+        self.validate_timestamps = validate;
+    }
+
+    /// Sets whether measurements are checked with `Measurement::validate` before sending
+    /// anything, so e.g. a measurement without fields does not fail the whole batch on the
+    /// server. Disabled by default.
+    pub fn set_validate_measurements(&mut self, validate: bool) {
+        self.validate_measurements = validate;
+    }
+
+    /// Sets whether nanosecond write precision is sent as `ns` instead of `n`, as different
+    /// InfluxDB versions accept different tokens. Disabled by default.
+    pub fn set_nanoseconds_as_ns(&mut self, ns: bool) {
+        self.nanoseconds_token = if ns { "ns" } else { "n" };
+    }
+
+    fn precision_token(&self, precision: &Precision) -> String {
+        match *precision {
+            // 2.x accepts `ns` only
+            Precision::Nanoseconds if self.token.is_some() => "ns".to_string(),
+            Precision::Nanoseconds => self.nanoseconds_token.to_string(),
+            _ => precision.to_string()
         }
     }
-}
 
-impl<'a> Client for HttpClient<'a> {
-    fn query(&self, q: String, epoch: Option<Precision>) -> ClientReadResult {
-        let host = self.get_host();
+    fn check_timestamps(&self, measurements: &[Measurement], precision: Option<Precision>) -> Result<(), ClientError> {
+        let precision = precision.unwrap_or(Precision::Nanoseconds);
+
+        for measurement in measurements {
+            match measurement.timestamp {
+                Some(t) => {
+                    let seconds = precision.to_seconds(t);
+                    if seconds < MIN_PLAUSIBLE_SECONDS || seconds > MAX_PLAUSIBLE_SECONDS {
+                        return Err(ClientError::ImplausibleTimestamp(t));
+                    }
+                }
+                _ => {}
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Restricts values of the tag to the given ones, so e.g. a typo in `env` is rejected
+    /// instead of creating a new series.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use influent::create_client;
+    /// use influent::client::Credentials;
+    ///
+    /// let credentials = Credentials { username: "gobwas", password: "xxx", database: "mydb" };
+    /// let mut client = create_client(credentials, vec!["http://localhost:8086"]);
+    ///
+    /// client.set_allowed_tag_values("env", vec!["prod", "staging", "dev"]);
+    /// ```
+    pub fn set_allowed_tag_values(&mut self, tag: &'a str, values: Vec<&'a str>) {
+        self.allowed_tag_values.insert(tag, values);
+    }
+
+    fn check_tags(&self, measurements: &[Measurement]) -> Result<(), ClientError> {
+        for measurement in measurements {
+            for (tag, value) in measurement.tags.iter() {
+                match self.allowed_tag_values.get(tag) {
+                    Some(values) if !values.contains(value) => {
+                        return Err(ClientError::DisallowedTagValue(tag.to_string(), value.to_string()));
+                    }
+                    _ => {}
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Sets retention policy the measurements are written to. Default retention policy
+    /// of the database is used, if not set.
+    pub fn set_retention_policy(&mut self, retention_policy: Option<&'a str>) {
+        self.retention_policy = retention_policy;
+    }
+
+    /// Sets consistency of the writes to InfluxDB Enterprise cluster. Server default
+    /// is used, if not set.
+    pub fn set_consistency(&mut self, consistency: Option<Consistency>) {
+        self.consistency = consistency;
+    }
+
+    /// Sets where credentials are sent. Defaults to `AuthMode::Header`, so they do not appear
+    /// in the URL.
+    pub fn set_auth_mode(&mut self, mode: AuthMode) {
+        self.auth_mode = mode;
+    }
+
+    /// Sets signer of the outgoing requests.
+    pub fn set_signer(&mut self, signer: Box<Signer>) {
+        self.signer = signer;
+    }
+
+    /// Sets parser of the query results used by `query_typed`, e.g. a faster one for large
+    /// responses. Defaults to `JsonParser`.
+    pub fn set_result_parser(&mut self, parser: Box<ResultParser>) {
+        self.result_parser = parser;
+    }
+
+    /// Sets backoff between retries of the failed write. Writes failed because of communication
+    /// or server error are retried with the same body. By default writes are not retried.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use std::time::Duration;
+    /// use influent::create_client;
+    /// use influent::client::Credentials;
+    /// use influent::client::backoff::ExponentialBackoff;
+    ///
+    /// let credentials = Credentials { username: "gobwas", password: "xxx", database: "mydb" };
+    /// let mut client = create_client(credentials, vec!["http://localhost:8086"]);
+    ///
+    /// client.set_backoff(Box::new(ExponentialBackoff::new(Duration::from_millis(100), Duration::from_secs(5), 3)));
+    /// ```
+    pub fn set_backoff(&mut self, backoff: Box<Backoff>) {
+        self.backoff = backoff;
+    }
+
+    /// Sets timeout of reading and writing requests, after which they fail with
+    /// `ClientError::Timeout`, and are retried and failed over as communication errors.
+    /// Requests are not limited by default; backend may not support timeouts at all.
+    pub fn set_timeout(&mut self, timeout: Option<Duration>) {
+        self.hurl.set_timeout(timeout);
+    }
+
+    /// Sets for how long resolved addresses of the hosts are reused, e.g. for hosts with DNS name
+    /// of a moving endpoint. Hosts are resolved on every request by default; backend may not
+    /// support caching at all.
+    pub fn set_dns_ttl(&mut self, ttl: Option<Duration>) {
+        self.hurl.set_dns_ttl(ttl);
+    }
+
+    /// Sets limiter of the write requests rate. Every batch of `max_batch` measurements and every
+    /// retry is a separate request. Writes are not limited by default.
+    pub fn set_rate_limiter(&mut self, limiter: Option<RateLimiter>) {
+        self.rate_limiter = limiter;
+    }
+
+    /// Returns body, which `write_one` would send for the measurement, e.g. for debugging,
+    /// or the error it would fail with.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use influent::create_client;
+    /// use influent::client::Credentials;
+    /// use influent::measurement::Measurement;
+    ///
+    /// let credentials = Credentials { username: "gobwas", password: "xxx", database: "mydb" };
+    /// let client = create_client(credentials, vec!["http://localhost:8086"]);
+    ///
+    /// let measurement = Measurement::new("cpu").with_tag("host", "a").with_field("load", 0.5);
+    ///
+    /// assert_eq!("cpu,host=a load=0.5", client.serialize(&measurement).unwrap());
+    /// ```
+    pub fn serialize(&self, measurement: &Measurement) -> Result<String, SerializeError> {
+        self.serializer.try_serialize_many(slice::from_ref(measurement))
+            .map(|(body, _)| body)
+            .map_err(|(_, err)| err)
+    }
+
+    fn auth(&self) -> Option<Auth<'a>> {
+        if self.credentials.is_anonymous() || self.auth_mode == AuthMode::Query {
+            return None;
+        }
+
+        Some(Auth {
+            username: self.credentials.username,
+            password: self.credentials.password
+        })
+    }
+
+    fn sign(&self, request: &mut Request, path: &str) {
+        match self.token {
+            Some(ref token) => {
+                request.headers.insert("Authorization".to_string(), format!("Token {}", token.token));
+            }
+            None => {}
+        };
+
+        match request.query {
+            Some(ref mut query) if self.auth_mode == AuthMode::Query && !self.credentials.is_anonymous() => {
+                query.insert("u", self.credentials.username.to_string());
+                query.insert("p", self.credentials.password.to_string());
+            }
+            _ => {}
+        };
+
+        let headers = self.signer.sign(&request.method, path, request.body.as_ref().map(|body| &body[..]));
+
+        for (name, value) in headers {
+            request.headers.insert(name, value);
+        }
+    }
+
+    /// Checks that InfluxDB is reachable, returning its version, e.g. for readiness probes.
+    /// Hosts are tried in turn, so the error is returned only if none of them responds.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use influent::create_client;
+    /// use influent::client::Credentials;
+    ///
+    /// let credentials = Credentials { username: "gobwas", password: "xxx", database: "mydb" };
+    /// let client = create_client(credentials, vec!["http://localhost:8086"]);
+    ///
+    /// match client.ping() {
+    ///     Ok(version) => println!("InfluxDB {} is up", version),
+    ///     Err(err) => println!("InfluxDB is down: {:?}", err)
+    /// }
+    /// ```
+    pub fn ping(&self) -> Result<String, ClientError> {
+        let mut last = None;
+
+        for host in self.hosts_in_turn() {
+            let url = host.to_string() + "/ping";
+
+            let mut request = Request {
+                url: &url,
+                method: Method::GET,
+                auth: None,
+                query: None,
+                headers: HashMap::new(),
+                body: None
+            };
+
+            self.sign(&mut request, "/ping");
+
+            let err = match self.hurl.request(request) {
+                Ok(ref resp) if resp.status == 204 || resp.status == 200 => {
+                    return resp.header(VERSION_HEADER)
+                        .map(|version| version.to_string())
+                        .ok_or(ClientError::Unexpected(format!("response has no {} header", VERSION_HEADER)));
+                },
+                Ok(ref resp) => status_error(resp),
+                Err(reason) => transport_error(reason)
+            };
+
+            last = Some(err);
+        }
+
+        Err(last.unwrap_or(ClientError::Unknown))
+    }
+
+    /// Writes the measurement and then queries it back to confirm it was persisted.
+    ///
+    /// Trades latency for durability assurance, so it is meant for low-volume critical writes.
+    /// Measurement must have a nanosecond timestamp, which is used along with its tags to find it
+    /// in the retention policy of the client, or in the bucket of InfluxDB 2.x.
+    pub fn write_and_verify(&self, measurement: Measurement) -> ClientWriteResult {
+        let timestamp = match measurement.timestamp {
+            Some(t) => t,
+            None => return Err(ClientError::Unverified("measurement without timestamp could not be verified".to_string()))
+        };
+
+        let tags: Vec<(&str, &str)> = measurement.tags.iter().map(|(tag, value)| (*tag, *value)).collect();
+
+        let mut conditions = vec![format!("time = {}", timestamp)];
+        conditions.extend(tag_conditions(&tags));
+
+        let from = match self.retention_policy {
+            Some(rp) => format!("{}.{}", influxql_quote_ident(rp), influxql_quote_ident(&measurement.key)),
+            None => influxql_quote_ident(&measurement.key)
+        };
+
+        let q = format!("select * from {} where {}", from, conditions.join(" and "));
+        let key = measurement.key.to_string();
+
+        let report = try!(self.write_one(measurement, None));
+        let result = try!(self.query_typed(q.clone(), Some(Precision::Nanoseconds)));
+
+        let mut found = 0;
+
+        for statement in result.results.iter() {
+            match statement.error {
+                Some(ref error) => return Err(ClientError::Unverified(format!("\"{}\" failed: {}", q, error))),
+                None => {}
+            };
+
+            for series in statement.series.iter().filter(|series| series.name == key) {
+                let time = series.columns.iter().position(|column| column == "time");
+
+                found += series.values.iter()
+                    .filter(|values| match time.and_then(|i| values.get(i)) {
+                        Some(&QueryValue::Integer(t)) => t == timestamp,
+                        _ => false
+                    })
+                    .count();
+            }
+        }
+
+        match found {
+            0 => Err(ClientError::Unverified(format!("measurement was not found with \"{}\"", q))),
+            _ => Ok(report)
+        }
+    }
+
+    /// Deletes points of the measurement, which have all the given tags.
+    pub fn delete(&self, key: &str, tags: &[(&str, &str)]) -> ClientReadResult {
+        let mut q = format!("delete from {}", influxql_quote_ident(key));
+
+        let conditions = tag_conditions(tags);
+        if !conditions.is_empty() {
+            q.push_str(" where ");
+            q.push_str(&conditions.join(" and "));
+        }
+
+        self.query(q, None)
+    }
+
+    /// Queries the database with bound parameters, which are referenced in the query as
+    /// `$name` and sent JSON-encoded in `params`, so values need no quoting.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use std::collections::BTreeMap;
+    /// use influent::create_client;
+    /// use influent::client::{Credentials, Precision};
+    /// use influent::measurement::Value;
+    ///
+    /// let credentials = Credentials { username: "gobwas", password: "xxx", database: "mydb" };
+    /// let client = create_client(credentials, vec!["http://localhost:8086"]);
+    ///
+    /// let mut params = BTreeMap::new();
+    /// params.insert("host", Value::String("server01"));
+    /// params.insert("min", Value::Float(0.5));
+    ///
+    /// let _ = client.query_with_params("select * from cpu where host = $host and load > $min".to_string(), &params, Some(Precision::Seconds));
+    /// ```
+    pub fn query_with_params(&self, q: String, params: &BTreeMap<&str, Value>, epoch: Option<Precision>) -> ClientReadResult {
+        self.send_query(q, Some(params_json(params)), epoch)
+    }
+
+    fn send_query(&self, q: String, params: Option<String>, epoch: Option<Precision>) -> ClientReadResult {
+        let mut last = None;
+
+        // next host is tried on communication and server errors
+        for host in self.hosts_in_turn() {
+            let url = host.to_string() + "/query";
+            let mut request = self.query_request(&url, q.clone(), epoch);
+
+            match (request.query.as_mut(), params.as_ref()) {
+                (Some(query), Some(params)) => {
+                    query.insert("params", params.clone());
+                }
+                _ => {}
+            };
+
+            let err = match self.hurl.request(request) {
+                Ok(ref resp) if resp.status == 200 => return expect_json(resp).map(|_| resp.to_string()),
+                Ok(ref resp) if resp.status == 400 => return Err(ClientError::Syntax(error_reason(resp))),
+                Ok(ref resp) if resp.status >= 500 => status_error(resp),
+                Ok(ref resp) => return Err(status_error(resp)),
+                Err(reason) => transport_error(reason)
+            };
+
+            last = Some(err);
+        }
+
+        Err(last.unwrap_or(ClientError::Unknown))
+    }
+
+    /// Queries the database, copying response body into the writer without buffering it.
+    ///
+    /// Query is made with `chunked=true`, so the body is a sequence of JSON objects,
+    /// separated by newlines. Useful for piping large exports into a file or a socket.
+    pub fn query_stream_into<W: Write>(&self, q: String, epoch: Option<Precision>, w: &mut W) -> Result<(), ClientError> {
+        // part of the body could be already copied on failure, so there is no failover
+        let url = self.hosts_in_turn()[0].to_string() + "/query";
+        let mut request = self.query_request(&url, q, epoch);
+
+        match request.query {
+            Some(ref mut query) => {
+                query.insert("chunked", "true".to_string());
+            }
+            _ => {}
+        };
 
+        match self.hurl.request_into(request, w) {
+            Ok(ref resp) if resp.status == 200 => Ok(()),
+            Ok(ref resp) if resp.status == 400 => Err(ClientError::Syntax(error_reason(resp))),
+            Ok(ref resp) => Err(status_error(resp)),
+            Err(reason) => Err(transport_error(reason))
+        }
+    }
+
+    fn query_request<'b>(&'b self, url: &'b str, q: String, epoch: Option<Precision>) -> Request<'b> {
         let mut query = HashMap::new();
         query.insert("db", self.credentials.database.to_string());
         query.insert("q", q);
 
+        // epoch of nanoseconds is documented as `ns` only, unlike write precision
         match epoch {
+            Some(Precision::Nanoseconds) => {
+                query.insert("epoch", "ns".to_string());
+            }
             Some(ref epoch) => {
                 query.insert("epoch", epoch.to_string());
             }
             _ => {}
         };
 
-        let request = Request {
-            url: &*{host.to_string() + "/query"},
+        let mut request = Request {
+            url: url,
             method: Method::GET,
-            auth: Some(Auth {
-                username: self.credentials.username,
-                password: self.credentials.password
-            }),
+            auth: self.auth(),
             query: Some(query),
+            headers: HashMap::new(),
             body: None
         };
 
-        match self.hurl.request(request) {
-            Ok(ref resp) if resp.status == 200 => Ok(resp.to_string()),
-            Ok(ref resp) if resp.status == 400 => Err(ClientError::Syntax(resp.to_string())),
-            Ok(ref resp) => Err(ClientError::Unexpected(format!("Unexpected response. Status: {}; Body: \"{}\"", resp.status, resp.to_string()))),
-            Err(reason) => Err(ClientError::Communication(reason))
-        }
+        self.sign(&mut request, "/query");
+
+        request
     }
 
-    fn write_one(&self, measurement: Measurement, precision: Option<Precision>) -> ClientWriteResult {
-        self.write_many(&[measurement], precision)
+    /// Writes measurements without their timestamps, so the server assigns its own time,
+    /// while measurements keep theirs.
+    ///
+    /// Measurements are serialized one per line, regardless of the serializer's `try_serialize_many`.
+    pub fn write_many_omit_timestamps(&self, measurements: &[Measurement], precision: Option<Precision>) -> ClientWriteResult {
+        self.write(measurements, precision, true)
     }
 
-    fn write_many(&self, measurements: &[Measurement], precision: Option<Precision>) -> ClientWriteResult {
-        let host = self.get_host();
+    /// Writes measurements, which could be serialized with `Serializer::try_serialize`, skipping
+    /// the rest instead of failing the whole batch. Returns report of the write along with
+    /// indexes of the skipped measurements and reasons.
+    ///
+    /// Measurements are serialized one per line, regardless of the serializer's `try_serialize_many`.
+    pub fn try_write_many(&self, measurements: &[Measurement], precision: Option<Precision>) -> Result<(WriteReport, Vec<(usize, SerializeError)>), ClientError> {
+        if measurements.is_empty() {
+            return Ok((WriteReport::empty(), vec![]));
+        }
 
-        for chunk in measurements.chunks(self.max_batch as usize) {
-            let mut lines = Vec::new();
+        try!(self.check(measurements, precision, false));
 
-            for measurement in chunk {
-                lines.push(self.serializer.serialize(measurement));
+        let mut lines = vec![];
+        let mut skipped = vec![];
+
+        for (i, measurement) in measurements.iter().enumerate() {
+            match self.serializer.try_serialize(measurement) {
+                // skipped by the serializer itself, e.g. measurement without fields
+                Ok(ref line) if line.is_empty() => {},
+                Ok(line) => lines.push((i, line)),
+                Err(err) => skipped.push((i, err))
             }
+        }
 
-            let mut query = HashMap::new();
-            query.insert("db", self.credentials.database.to_string());
+        let mut written = 0;
+        let mut points = Some(0);
 
-            match precision {
-                Some(ref precision) => {
-                    query.insert("precision", precision.to_string());
-                }
-                _ => {}
-            };
+        for chunk in lines.chunks(self.max_batch as usize) {
+            let body: Vec<&str> = chunk.iter().map(|&(_, ref line)| &line[..]).collect();
 
-            let request = Request {
-                url: &*{host.to_string() + "/write"},
-                method: Method::POST,
-                auth: Some(Auth {
-                    username: self.credentials.username,
-                    password: self.credentials.password
-                }),
-                query: Some(query),
-                body: Some(lines.connect("\n"))
-            };
+            match self.send_write(&body.join("\n"), precision) {
+                Ok(resp) => {
+                    written += chunk.len();
+                    points = points_written(&resp, points);
+                },
+                // resume from the first measurement of the failed batch
+                Err(err) if written > 0 => {
+                    return Err(ClientError::Interrupted(WriteReport { written: written, resume_index: Some(chunk[0].0), points_written: points }, Box::new(err)));
+                },
+                Err(err) => return Err(err)
+            }
+        }
 
-            match self.hurl.request(request) {
-                Ok(ref resp) if resp.status == 204 => {},
-                Ok(ref resp) if resp.status == 200 => return Err(ClientError::CouldNotComplete(resp.to_string())),
-                Ok(ref resp) if resp.status == 400 => return Err(ClientError::Syntax(resp.to_string())),
-                Ok(ref resp) => return Err(ClientError::Unexpected(format!("Unexpected response. Status: {}; Body: \"{}\"", resp.status, resp.to_string()))),
-                Err(reason) => return Err(ClientError::Communication(reason))
-            };
+        Ok((WriteReport { written: written, resume_index: None, points_written: points }, skipped))
+    }
+
+    fn check(&self, measurements: &[Measurement], precision: Option<Precision>, omit_timestamps: bool) -> Result<(), ClientError> {
+        try!(check_keys(measurements));
+
+        if self.validate_measurements {
+            for measurement in measurements {
+                try!(measurement.validate().map_err(ClientError::Invalid));
+            }
+        }
+
+        if self.validate_timestamps && self.token.is_some() && !omit_timestamps {
+            try!(self.check_timestamps(measurements, precision));
+        }
+
+        if !self.allowed_tag_values.is_empty() {
+            try!(self.check_tags(measurements));
         }
 
         Ok(())
     }
+
+    fn write(&self, measurements: &[Measurement], precision: Option<Precision>, omit_timestamps: bool) -> ClientWriteResult {
+        if measurements.is_empty() {
+            return Ok(WriteReport::empty());
+        }
+
+        try!(self.check(measurements, precision, omit_timestamps));
+
+        // all batches are serialized up front, so nothing is sent if any measurement fails;
+        // batch is the index of its first measurement, the body and count of measurements in it
+        let mut batches = vec![];
+
+        for (n, chunk) in measurements.chunks(self.max_batch as usize).enumerate() {
+            let start = n * self.max_batch as usize;
+
+            let serialized = if omit_timestamps {
+                self.serialize_without_timestamps(chunk)
+            } else {
+                self.serializer.try_serialize_many(chunk)
+            };
+
+            match serialized {
+                // every measurement is skipped by the serializer
+                Ok((_, 0)) => {},
+                Ok((body, count)) => batches.push((start, body, count)),
+                Err((i, err)) => return Err(ClientError::Serialize(start + i, err))
+            }
+        }
+
+        let mut written = 0;
+        let mut points = Some(0);
+
+        for (start, body, count) in batches {
+            match self.send_write(&body, precision) {
+                Ok(resp) => {
+                    written += count;
+                    points = points_written(&resp, points);
+                },
+                // previous batches are persisted, so caller can resume from the failed one
+                Err(err) if written > 0 => {
+                    return Err(ClientError::Interrupted(WriteReport { written: written, resume_index: Some(start), points_written: points }, Box::new(err)));
+                },
+                Err(err) => return Err(err)
+            }
+        }
+
+        Ok(WriteReport { written: written, resume_index: None, points_written: points })
+    }
+
+    fn serialize_without_timestamps(&self, measurements: &[Measurement]) -> Result<(String, usize), (usize, SerializeError)> {
+        let mut lines = vec![];
+
+        for (i, measurement) in measurements.iter().enumerate() {
+            match self.serializer.try_serialize_without_timestamp(measurement) {
+                Ok(ref line) if line.is_empty() => {},
+                Ok(line) => lines.push(line),
+                Err(err) => return Err((i, err))
+            }
+        }
+
+        Ok((lines.join("\n"), lines.len()))
+    }
+
+    // Sends the body, which is serialized once, so retries resend exactly the same points.
+    // Hosts are tried in turn on communication and server errors, then the whole round is retried
+    // according to the backoff.
+    fn send_write(&self, body: &str, precision: Option<Precision>) -> Result<Response, ClientError> {
+        let mut attempt = 0;
+
+        loop {
+            let mut last = None;
+
+            for host in self.hosts_in_turn() {
+                match self.rate_limiter {
+                    Some(ref limiter) => try!(limiter.acquire()),
+                    None => {}
+                };
+
+                let mut query = HashMap::new();
+                let mut headers = HashMap::new();
+
+                let path = match self.token {
+                    Some(ref token) => {
+                        query.insert(token.org.param(), token.org.value().to_string());
+
+                        match self.org_header {
+                            Some(header) => {
+                                headers.insert(header.to_string(), token.org.value().to_string());
+                            }
+                            None => {}
+                        };
+
+                        query.insert("bucket", token.bucket.to_string());
+                        "/api/v2/write"
+                    }
+                    None => {
+                        query.insert("db", self.credentials.database.to_string());
+                        "/write"
+                    }
+                };
+
+                match precision {
+                    Some(ref precision) => {
+                        query.insert("precision", self.precision_token(precision));
+                    }
+                    _ => {}
+                };
+
+                match self.retention_policy {
+                    Some(rp) => {
+                        query.insert("rp", rp.to_string());
+                    }
+                    _ => {}
+                };
+
+                match self.consistency {
+                    Some(ref consistency) => {
+                        query.insert("consistency", consistency.to_string());
+                    }
+                    _ => {}
+                };
+
+                let mut request = Request {
+                    url: &*{host.to_string() + path},
+                    method: Method::POST,
+                    auth: self.auth(),
+                    query: Some(query),
+                    headers: headers,
+                    body: Some(body.to_string())
+                };
+
+                self.sign(&mut request, path);
+
+                let err = match self.hurl.request(request) {
+                    Ok(resp) => match resp.status {
+                        204 => return Ok(resp),
+                        200 => match expect_json(&resp) {
+                            Ok(_) => return Err(ClientError::CouldNotComplete(error_reason(&resp))),
+                            Err(err) => return Err(err)
+                        },
+                        400 => return Err(ClientError::Syntax(error_reason(&resp))),
+                        status if status >= 500 => status_error(&resp),
+                        _ => return Err(status_error(&resp))
+                    },
+                    Err(reason) => transport_error(reason)
+                };
+
+                last = Some(err);
+            }
+
+            attempt += 1;
+
+            match self.backoff.next_delay(attempt) {
+                Some(delay) => thread::sleep(delay),
+                None => return Err(last.unwrap_or(ClientError::Unknown))
+            }
+        }
+    }
+
+    // Returns hosts starting from the next one in rotation, so requests are spread over them.
+    fn hosts_in_turn(&self) -> Vec<&'a str> {
+        if self.hosts.is_empty() {
+            panic!("Could not get host");
+        }
+
+        let first = self.next_host.get() % self.hosts.len();
+        self.next_host.set(first + 1);
+
+        self.hosts[first..].iter().chain(self.hosts[..first].iter()).map(|host| *host).collect()
+    }
+}
+
+// InfluxQL conditions matching the tags.
+fn tag_conditions(tags: &[(&str, &str)]) -> Vec<String> {
+    tags.iter()
+        .map(|&(tag, value)| format!("{} = {}", influxql_quote_ident(tag), influxql_quote_literal(value)))
+        .collect()
+}
+
+// InfluxDB puts short error summary in the header, which is handy when body is empty or large.
+fn error_reason(resp: &Response) -> String {
+    match resp.header(ERROR_HEADER) {
+        Some(reason) => reason.to_string(),
+        None => resp.to_string()
+    }
+}
+
+// Not every server reports the count, so it is known only if every response has it.
+fn points_written(resp: &Response, total: Option<usize>) -> Option<usize> {
+    match (total, resp.header(POINTS_WRITTEN_HEADER).and_then(|n| n.trim().parse::<usize>().ok())) {
+        (Some(total), Some(n)) => Some(total + n),
+        _ => None
+    }
+}
+
+// Misconfigured proxy may respond with an HTML page instead of InfluxDB's JSON.
+fn transport_error(reason: String) -> ClientError {
+    if reason == TIMED_OUT {
+        return ClientError::Timeout;
+    }
+
+    ClientError::Communication(reason)
+}
+
+fn status_error(resp: &Response) -> ClientError {
+    ClientError::Http {
+        status: resp.status,
+        reason: error_reason(resp),
+        body: resp.body.clone()
+    }
+}
+
+fn params_json(params: &BTreeMap<&str, Value>) -> String {
+    let object: BTreeMap<String, Json> = params.iter()
+        .map(|(name, value)| {
+            let value = match *value {
+                Value::String(s)          => Json::String(s.to_string()),
+                Value::Float(f)           => Json::F64(f),
+                Value::Integer(i)         => Json::I64(i),
+                Value::UnsignedInteger(u) => Json::U64(u),
+                Value::Boolean(b)         => Json::Boolean(b)
+            };
+
+            (name.to_string(), value)
+        })
+        .collect();
+
+    Json::Object(object).to_string()
+}
+
+fn expect_json(resp: &Response) -> Result<(), ClientError> {
+    let content_type = resp.header("Content-Type").map(|t| t.to_string());
+
+    let is_json = match content_type {
+        Some(ref t) => t.contains("json"),
+        None => !resp.body.trim_start().starts_with("<")
+    };
+
+    if is_json {
+        return Ok(());
+    }
+
+    Err(ClientError::UnexpectedResponse {
+        content_type: content_type,
+        snippet: resp.body.chars().take(SNIPPET_LEN).collect()
+    })
+}
+
+impl<'a> Client for HttpClient<'a> {
+    fn query(&self, q: String, epoch: Option<Precision>) -> ClientReadResult {
+        self.send_query(q, None, epoch)
+    }
+
+    fn result_parser(&self) -> &ResultParser {
+        &*self.result_parser
+    }
+
+    fn write_one(&self, measurement: Measurement, precision: Option<Precision>) -> ClientWriteResult {
+        self.write_many(&[measurement], precision)
+    }
+
+    fn write_many(&self, measurements: &[Measurement], precision: Option<Precision>) -> ClientWriteResult {
+        self.write(measurements, precision, false)
+    }
 }
 
 
@@ -139,12 +935,22 @@ impl<'a> Client for HttpClient<'a> {
 mod tests {
     use ::serializer::Serializer;
     use ::client::{Client};
-    use super::HttpClient;
-    use ::client::{Credentials, Precision};
-    use ::hurl::{Hurl, Request, Response, HurlResult};
-    use ::measurement::Measurement;
-    use std::cell::Cell;
+    use super::{HttpClient, Signer, AuthMode};
+    use ::client::{Credentials, TokenCredentials, Org, Precision, Consistency, ClientError, WriteReport};
+    use ::hurl::{Hurl, Request, Response, HurlResult, Method};
+    use ::measurement::{Measurement, Value, ValidationError};
+    use ::query::{QueryValue, QueryResult, StatementResult, ResultParser};
+    use ::serializer::line::LineSerializer;
+    use ::serializer::{SerializeError, NoFieldsPolicy};
+    use std::io::Write;
+    use std::f64;
+    use ::client::backoff::{Backoff, ConstantBackoff};
+    use ::client::rate_limit::{RateLimiter, RateLimitPolicy};
+    use std::cell::{Cell, RefCell};
+    use std::time::{Duration, Instant};
     use std::clone::Clone;
+    use std::rc::Rc;
+    use std::collections::{BTreeMap, HashMap};
 
     const serialized : &'static str = "serialized";
 
@@ -161,20 +967,25 @@ mod tests {
     }
 
     impl Serializer for MockSerializer {
-        fn serialize(&self, measurement: &Measurement) -> String {
+        fn serialize_into(&self, measurement: &Measurement, w: &mut Write) -> Result<(), SerializeError> {
             println!("serializing: {:?}", measurement);
             self.serialize_count.set(self.serialize_count.get() + 1);
-            serialized.to_string()
+            try!(w.write_all(serialized.as_bytes()));
+            Ok(())
+        }
+
+        fn try_serialize_without_timestamp(&self, measurement: &Measurement) -> Result<String, SerializeError> {
+            self.try_serialize(measurement)
         }
     }
 
     struct MockHurl {
         request_count: Cell<u16>,
-        result: Box<Fn() -> HurlResult>
+        result: Box<Fn(&Request) -> HurlResult>
     }
 
     impl MockHurl {
-        fn new(result: Box<Fn() -> HurlResult>) -> MockHurl {
+        fn new(result: Box<Fn(&Request) -> HurlResult>) -> MockHurl {
             MockHurl {
                 request_count: Cell::new(0),
                 result: result
@@ -187,11 +998,11 @@ mod tests {
             self.request_count.set(self.request_count.get() + 1);
             println!("sending: {:?}", req);
             let ref f = self.result;
-            f()
+            f(&req)
         }
     }
 
-    fn before<'a>(result: Box<Fn() -> HurlResult>) -> HttpClient<'a> {        
+    fn before<'a>(result: Box<Fn(&Request) -> HurlResult>) -> HttpClient<'a> {        
         let credentials = Credentials {
             username: "gobwas",
             password: "1234",
@@ -206,18 +1017,1121 @@ mod tests {
 
     #[test]
     fn test_write_one() {
-        let mut client = before(Box::new(|| Ok(Response { status: 200, body: "Ok".to_string() })));
+        let mut client = before(Box::new(|_| Ok(Response { status: 200, headers: HashMap::new(), body: "Ok".to_string() })));
         client.add_host("http://localhost:8086");
         client.write_one(Measurement::new("key"), Some(Precision::Nanoseconds));
     }
 
     #[test]
     fn test_write_many() {
-        let mut client = before(Box::new(|| Ok(Response { status: 200, body: "Ok".to_string() })));
+        let mut client = before(Box::new(|_| Ok(Response { status: 200, headers: HashMap::new(), body: "Ok".to_string() })));
         client.add_host("http://localhost:8086");
         client.write_many(&[Measurement::new("key")], Some(Precision::Nanoseconds));
     }
-}
 
+    #[test]
+    fn test_write_many_interrupted() {
+        let count = Rc::new(Cell::new(0));
+        let counter = count.clone();
+
+        let mut client = before(Box::new(move |_| {
+            counter.set(counter.get() + 1);
+
+            match counter.get() {
+                1 => Ok(Response { status: 204, headers: HashMap::new(), body: "".to_string() }),
+                _ => Ok(Response { status: 500, headers: HashMap::new(), body: "Fail".to_string() })
+            }
+        }));
+        client.add_host("http://localhost:8086");
+        client.max_batch = 2;
+
+        let measurements = vec![Measurement::new("a"), Measurement::new("b"), Measurement::new("c"), Measurement::new("d"), Measurement::new("e")];
+
+        match client.write_many(&measurements, None) {
+            Err(ClientError::Interrupted(report, _)) => {
+                assert_eq!(2, report.written);
+                assert_eq!(Some(2), report.resume_index);
+            },
+            other => panic!("unexpected result: {:?}", other)
+        }
+
+        assert_eq!(2, count.get());
+    }
 
+    #[test]
+    fn test_write_many_single_request() {
+        let count = Rc::new(Cell::new(0));
+        let counter = count.clone();
 
+        let mut client = before(Box::new(move |req| {
+            counter.set(counter.get() + 1);
+            assert_eq!(100, req.body.as_ref().unwrap().split('\n').count());
+            Ok(Response { status: 204, headers: HashMap::new(), body: "".to_string() })
+        }));
+        client.add_host("http://localhost:8086");
+
+        let measurements: Vec<Measurement> = (0..100).map(|_| Measurement::new("key")).collect();
+
+        assert_eq!(100, client.write_many(&measurements, None).unwrap().written);
+        assert_eq!(1, count.get());
+    }
+
+    #[test]
+    fn test_write_points_written() {
+        let mut client = before(Box::new(|_| {
+            let mut headers = HashMap::new();
+            headers.insert("X-Influxdb-Points-Written".to_string(), "2".to_string());
+
+            Ok(Response { status: 204, headers: headers, body: "".to_string() })
+        }));
+        client.add_host("http://localhost:8086");
+        client.max_batch = 2;
+
+        let measurements = vec![Measurement::new("a"), Measurement::new("b"), Measurement::new("c"), Measurement::new("d")];
+        assert_eq!(Some(4), client.write_many(&measurements, None).unwrap().points_written);
+
+        let mut client = before(Box::new(|_| Ok(Response { status: 204, headers: HashMap::new(), body: "".to_string() })));
+        client.add_host("http://localhost:8086");
+
+        assert_eq!(None, client.write_one(Measurement::new("a"), None).unwrap().points_written);
+    }
+
+    struct MockBatchSerializer;
+
+    impl Serializer for MockBatchSerializer {
+        fn serialize_into(&self, _: &Measurement, _: &mut Write) -> Result<(), SerializeError> {
+            panic!("measurements should be serialized as a batch")
+        }
+
+        fn try_serialize_without_timestamp(&self, _: &Measurement) -> Result<String, SerializeError> {
+            panic!("measurements should be serialized as a batch")
+        }
+
+        fn try_serialize_many(&self, measurements: &[Measurement]) -> Result<(String, usize), (usize, SerializeError)> {
+            Ok((format!("batch of {}", measurements.len()), measurements.len()))
+        }
+    }
+
+    #[test]
+    fn test_write_serialize_many() {
+        let credentials = Credentials {
+            username: "gobwas",
+            password: "1234",
+            database: "test"
+        };
+
+        let hurl = MockHurl::new(Box::new(|req| {
+            assert_eq!(Some("batch of 2".to_string()), req.body);
+            Ok(Response { status: 204, headers: HashMap::new(), body: "".to_string() })
+        }));
+
+        let mut client = HttpClient::new(credentials, Box::new(MockBatchSerializer), Box::new(hurl));
+        client.add_host("http://localhost:8086");
+
+        client.write_many(&[Measurement::new("a"), Measurement::new("b")], None).unwrap();
+    }
+
+    #[test]
+    fn test_write_many_non_finite_float() {
+        let hurl = ::hurl::mock::MockHurl::new(Response { status: 204, headers: HashMap::new(), body: "".to_string() });
+        let requests = hurl.requests();
+
+        let credentials = Credentials { username: "gobwas", password: "1234", database: "test" };
+        let mut client = HttpClient::new(credentials, Box::new(LineSerializer::new()), Box::new(hurl));
+        client.add_host("http://localhost:8086");
+        client.max_batch = 1;
+
+        let measurements = vec![
+            Measurement::new("a").with_field("v", 1i64),
+            Measurement::new("b").with_field("v", f64::NAN),
+            Measurement::new("c").with_field("v", 0.5f64).with_field("w", f64::INFINITY)
+        ];
+
+        // nothing is sent, even the batches before the failed one
+        match client.write_many(&measurements, None) {
+            Err(ClientError::Serialize(1, SerializeError::NonFiniteFloat { ref key, ref field })) => assert_eq!(("b", "v"), (&key[..], &field[..])),
+            other => panic!("unexpected result: {:?}", other)
+        }
+        assert!(requests.borrow().is_empty());
+
+        let report = client.write_many(&[measurements[0].clone()], None).unwrap();
+        assert_eq!(1, report.written);
+        assert_eq!(Some("a v=1i".to_string()), requests.borrow()[0].body);
+    }
+
+    #[test]
+    fn test_write_no_fields() {
+        let hurl = ::hurl::mock::MockHurl::new(Response { status: 204, headers: HashMap::new(), body: "".to_string() });
+        let requests = hurl.requests();
+
+        let credentials = Credentials { username: "gobwas", password: "1234", database: "test" };
+        let mut client = HttpClient::new(credentials, Box::new(LineSerializer::new()), Box::new(hurl));
+        client.add_host("http://localhost:8086");
+
+        // NoFieldsPolicy::Fail is the default
+        match client.write_one(Measurement::new("key").with_tag("tag", "value"), None) {
+            Err(ClientError::Serialize(0, SerializeError::NoFields { ref key })) => assert_eq!("key", key),
+            other => panic!("unexpected result: {:?}", other)
+        }
+
+        match client.write_many_omit_timestamps(&[Measurement::new("a").with_field("v", 1i64), Measurement::new("b").with_timestamp(10)], None) {
+            Err(ClientError::Serialize(1, SerializeError::NoFields { .. })) => {},
+            other => panic!("unexpected result: {:?}", other)
+        }
+
+        assert!(requests.borrow().is_empty());
+    }
+
+    #[test]
+    fn test_write_many_skipped() {
+        let hurl = ::hurl::mock::MockHurl::new(Response { status: 204, headers: HashMap::new(), body: "".to_string() });
+        let requests = hurl.requests();
+
+        let mut serializer = LineSerializer::new();
+        serializer.set_no_fields_policy(NoFieldsPolicy::Skip);
+
+        let credentials = Credentials { username: "gobwas", password: "1234", database: "test" };
+        let mut client = HttpClient::new(credentials, Box::new(serializer), Box::new(hurl));
+        client.add_host("http://localhost:8086");
+        client.max_batch = 2;
+
+        let measurements = vec![
+            Measurement::new("a").with_field("v", 1i64),
+            Measurement::new("b"),
+            Measurement::new("c"),
+            Measurement::new("d")
+        ];
+
+        // skipped measurements are not counted, and the batch of them is not sent at all
+        let report = client.write_many(&measurements, None).unwrap();
+        assert_eq!(1, report.written);
+
+        let requests = requests.borrow();
+        assert_eq!(1, requests.len());
+        assert_eq!(Some("a v=1i".to_string()), requests[0].body);
+    }
+
+    #[test]
+    fn test_allowed_tag_values() {
+        let mut client = before(Box::new(|_| Ok(Response { status: 204, headers: HashMap::new(), body: "".to_string() })));
+        client.add_host("http://localhost:8086");
+        client.set_allowed_tag_values("env", vec!["prod", "staging", "dev"]);
+
+        let mut measurement = Measurement::new("key");
+        measurement.add_tag("env", "prd");
+
+        match client.write_one(measurement, None) {
+            Err(ClientError::DisallowedTagValue(tag, value)) => {
+                assert_eq!("env", tag);
+                assert_eq!("prd", value);
+            },
+            other => panic!("unexpected result: {:?}", other)
+        }
+
+        let mut measurement = Measurement::new("key");
+        measurement.add_tag("env", "prod");
+        measurement.add_tag("host", "anything");
+
+        client.write_one(measurement, None).unwrap();
+    }
+
+    #[test]
+    fn test_write_key_with_newline() {
+        let mut client = before(Box::new(|_| panic!("request should not be sent")));
+        client.add_host("http://localhost:8086");
+
+        match client.write_one(Measurement::new("a\nb"), None) {
+            Err(ClientError::InvalidKey(key)) => assert_eq!("a\nb", key),
+            other => panic!("unexpected result: {:?}", other)
+        }
+
+        let mut measurement = Measurement::new("key");
+        measurement.add_tag("host", "server\n01");
+
+        match client.write_one(measurement, None) {
+            Err(ClientError::InvalidKey(key)) => assert_eq!("server\n01", key),
+            other => panic!("unexpected result: {:?}", other)
+        }
+    }
+
+    struct MockBackoff {
+        attempts: Rc<RefCell<Vec<u32>>>
+    }
+
+    impl Backoff for MockBackoff {
+        fn next_delay(&self, attempt: u32) -> Option<Duration> {
+            self.attempts.borrow_mut().push(attempt);
+
+            match attempt {
+                1 => Some(Duration::from_millis(10)),
+                2 => Some(Duration::from_millis(20)),
+                _ => None
+            }
+        }
+    }
+
+    #[test]
+    fn test_write_backoff() {
+        let bodies = Rc::new(RefCell::new(vec![]));
+        let requests = bodies.clone();
+
+        let mut client = before(Box::new(move |req| {
+            requests.borrow_mut().push(req.body.clone().unwrap());
+            Ok(Response { status: 503, headers: HashMap::new(), body: "".to_string() })
+        }));
+        client.add_host("http://localhost:8086");
+
+        let attempts = Rc::new(RefCell::new(vec![]));
+        client.set_backoff(Box::new(MockBackoff { attempts: attempts.clone() }));
+
+        let started = Instant::now();
+
+        match client.write_one(Measurement::new("key"), None) {
+            Err(ClientError::Http { status: 503, .. }) => {},
+            other => panic!("unexpected result: {:?}", other)
+        }
+
+        assert!(started.elapsed() >= Duration::from_millis(30));
+        assert_eq!(vec![1, 2, 3], *attempts.borrow());
+        assert_eq!(vec!["serialized", "serialized", "serialized"], *bodies.borrow());
+    }
+
+    #[test]
+    fn test_write_backoff_same_body() {
+        let measurement = Rc::new(RefCell::new(Measurement::new("key").with_field("v", 1i64).with_timestamp(1)));
+        let shared = measurement.clone();
+
+        let bodies = Rc::new(RefCell::new(vec![]));
+        let requests = bodies.clone();
+
+        let hurl = MockHurl::new(Box::new(move |req| {
+            requests.borrow_mut().push(req.body.clone().unwrap());
+
+            // changes of the measurement between attempts do not get into the retried body
+            let mut m = shared.borrow_mut();
+            m.add_field("v", 2i64);
+            m.add_tag("tag", "value");
+            m.set_timestamp(2);
+
+            Ok(Response { status: 503, headers: HashMap::new(), body: "".to_string() })
+        }));
+
+        let credentials = Credentials { username: "gobwas", password: "1234", database: "test" };
+        let mut client = HttpClient::new(credentials, Box::new(LineSerializer::new()), Box::new(hurl));
+        client.add_host("http://localhost:8086");
+        client.set_backoff(Box::new(ConstantBackoff::new(Duration::from_millis(1), 3)));
+
+        let m = measurement.borrow().clone();
+        match client.write_one(m, None) {
+            Err(ClientError::Http { status: 503, .. }) => {},
+            other => panic!("unexpected result: {:?}", other)
+        }
+
+        assert_eq!(vec!["key v=1i 1"; 4], *bodies.borrow());
+    }
+
+    #[test]
+    fn test_write_backoff_recovers() {
+        let count = Rc::new(Cell::new(0));
+        let counter = count.clone();
+
+        let mut client = before(Box::new(move |_| {
+            counter.set(counter.get() + 1);
+
+            match counter.get() {
+                1 => Err("connection reset by peer".to_string()),
+                2 => Ok(Response { status: 503, headers: HashMap::new(), body: "".to_string() }),
+                _ => Ok(Response { status: 204, headers: HashMap::new(), body: "".to_string() })
+            }
+        }));
+        client.add_host("http://localhost:8086");
+        client.set_backoff(Box::new(ConstantBackoff::new(Duration::from_millis(1), 5)));
+
+        assert_eq!(1, client.write_one(Measurement::new("key"), None).unwrap().written);
+        assert_eq!(3, count.get());
+    }
+
+    #[test]
+    fn test_write_backoff_not_retried() {
+        let count = Rc::new(Cell::new(0));
+        let counter = count.clone();
+
+        let mut client = before(Box::new(move |_| {
+            counter.set(counter.get() + 1);
+            Ok(Response { status: 400, headers: HashMap::new(), body: "bad line".to_string() })
+        }));
+        client.add_host("http://localhost:8086");
+        client.set_backoff(Box::new(MockBackoff { attempts: Rc::new(RefCell::new(vec![])) }));
+
+        match client.write_one(Measurement::new("key"), None) {
+            Err(ClientError::Syntax(_)) => {},
+            other => panic!("unexpected result: {:?}", other)
+        }
+
+        assert_eq!(1, count.get());
+    }
+
+    #[test]
+    fn test_write_nanoseconds_token() {
+        let precisions = Rc::new(RefCell::new(vec![]));
+        let requests = precisions.clone();
+
+        let mut client = before(Box::new(move |req| {
+            requests.borrow_mut().push(req.query.as_ref().unwrap().get("precision").unwrap().clone());
+            Ok(Response { status: 204, headers: HashMap::new(), body: "".to_string() })
+        }));
+        client.add_host("http://localhost:8086");
+
+        client.write_one(Measurement::new("key"), Some(Precision::Nanoseconds)).unwrap();
+
+        client.set_nanoseconds_as_ns(true);
+        client.write_one(Measurement::new("key"), Some(Precision::Nanoseconds)).unwrap();
+        client.write_one(Measurement::new("key"), Some(Precision::Microseconds)).unwrap();
+
+        assert_eq!(vec!["n", "ns", "u"], *precisions.borrow());
+    }
+
+    #[test]
+    fn test_write_precision() {
+        let precisions = Rc::new(RefCell::new(vec![]));
+        let requests = precisions.clone();
+
+        let mut client = before(Box::new(move |req| {
+            requests.borrow_mut().push(req.query.as_ref().unwrap().get("precision").cloned());
+            Ok(Response { status: 204, headers: HashMap::new(), body: "".to_string() })
+        }));
+        client.add_host("http://localhost:8086");
+
+        client.write_one(Measurement::new("key"), None).unwrap();
+
+        for precision in vec![Precision::Nanoseconds, Precision::Microseconds, Precision::Milliseconds, Precision::Seconds] {
+            client.write_one(Measurement::new("key"), Some(precision)).unwrap();
+        }
+
+        let expected = vec![None, Some("n"), Some("u"), Some("ms"), Some("s")];
+        assert_eq!(expected, precisions.borrow().iter().map(|p| p.as_ref().map(|p| &p[..])).collect::<Vec<_>>());
+    }
+
+    #[test]
+    fn test_write_retention_policy() {
+        let policies = Rc::new(RefCell::new(vec![]));
+        let requests = policies.clone();
+
+        let mut client = before(Box::new(move |req| {
+            requests.borrow_mut().push(req.query.as_ref().unwrap().get("rp").cloned());
+            Ok(Response { status: 204, headers: HashMap::new(), body: "".to_string() })
+        }));
+        client.add_host("http://localhost:8086");
+
+        client.write_one(Measurement::new("key"), None).unwrap();
+
+        client.set_retention_policy(Some("thirty_days"));
+        client.write_one(Measurement::new("key"), None).unwrap();
+
+        assert_eq!(vec![None, Some("thirty_days".to_string())], *policies.borrow());
+    }
+
+    #[test]
+    fn test_write_consistency() {
+        let consistencies = Rc::new(RefCell::new(vec![]));
+        let requests = consistencies.clone();
+
+        let mut client = before(Box::new(move |req| {
+            requests.borrow_mut().push(req.query.as_ref().unwrap().get("consistency").cloned());
+            Ok(Response { status: 204, headers: HashMap::new(), body: "".to_string() })
+        }));
+        client.add_host("http://localhost:8086");
+
+        client.write_one(Measurement::new("key"), None).unwrap();
+
+        for consistency in vec![Consistency::Any, Consistency::One, Consistency::Quorum, Consistency::All] {
+            client.set_consistency(Some(consistency));
+            client.write_one(Measurement::new("key"), None).unwrap();
+        }
+
+        let expected = vec![None, Some("any"), Some("one"), Some("quorum"), Some("all")];
+        assert_eq!(expected, consistencies.borrow().iter().map(|c| c.as_ref().map(|c| &c[..])).collect::<Vec<_>>());
+    }
+
+    #[test]
+    fn test_auth_mode() {
+        let mut client = before(Box::new(|req| {
+            let query = req.query.as_ref().unwrap();
+
+            match req.auth {
+                Some(ref auth) => {
+                    assert_eq!(("gobwas", "1234"), (auth.username, auth.password));
+                    assert_eq!((None, None), (query.get("u"), query.get("p")));
+                },
+                None => {
+                    assert_eq!(Some(&"gobwas".to_string()), query.get("u"));
+                    assert_eq!(Some(&"1234".to_string()), query.get("p"));
+                }
+            }
+
+            Ok(Response { status: 204, headers: HashMap::new(), body: "".to_string() })
+        }));
+        client.add_host("http://localhost:8086");
+
+        client.write_one(Measurement::new("key"), None).unwrap();
+
+        client.set_auth_mode(AuthMode::Query);
+        client.write_one(Measurement::new("key"), None).unwrap();
+    }
+
+    #[test]
+    fn test_write_v2() {
+        let hurl = MockHurl::new(Box::new(|req| {
+            assert_eq!("http://localhost:8086/api/v2/write", req.url);
+            assert_eq!(Some(&"Token secret".to_string()), req.headers.get("Authorization"));
+            assert!(req.auth.is_none());
+
+            let query = req.query.as_ref().unwrap();
+            assert_eq!(Some(&"myorg".to_string()), query.get("org"));
+            assert_eq!(Some(&"mybucket".to_string()), query.get("bucket"));
+            assert_eq!(Some(&"ns".to_string()), query.get("precision"));
+            assert_eq!(None, query.get("db"));
+
+            Ok(Response { status: 204, headers: HashMap::new(), body: "".to_string() })
+        }));
+
+        let credentials = TokenCredentials { token: "secret", org: Org::Name("myorg"), bucket: "mybucket" };
+        let mut client = HttpClient::new_v2(credentials, Box::new(MockSerializer::new()), Box::new(hurl));
+        client.add_host("http://localhost:8086");
+
+        client.write_one(Measurement::new("key"), Some(Precision::Nanoseconds)).unwrap();
+    }
+
+    #[test]
+    fn test_write_v2_org() {
+        let hurl = ::hurl::mock::MockHurl::new(Response { status: 204, headers: HashMap::new(), body: "".to_string() });
+        let requests = hurl.requests();
+
+        let credentials = TokenCredentials { token: "secret", org: Org::Id("0123456789abcdef"), bucket: "mybucket" };
+        let mut client = HttpClient::new_v2(credentials, Box::new(MockSerializer::new()), Box::new(hurl));
+        client.add_host("http://localhost:8086");
+
+        client.write_one(Measurement::new("key"), None).unwrap();
+
+        client.set_org_header(Some("X-Influxdb-Org"));
+        client.write_one(Measurement::new("key"), None).unwrap();
+
+        let requests = requests.borrow();
+
+        for request in requests.iter() {
+            assert_eq!(Some(&"0123456789abcdef".to_string()), request.query.get("orgID"));
+            assert_eq!(None, request.query.get("org"));
+        }
+
+        assert_eq!(None, requests[0].headers.get("X-Influxdb-Org"));
+        assert_eq!(Some(&"0123456789abcdef".to_string()), requests[1].headers.get("X-Influxdb-Org"));
+        assert_eq!(Some(&"Token secret".to_string()), requests[1].headers.get("Authorization"));
+    }
+
+    #[test]
+    fn test_write_https_host() {
+        let mut client = before(Box::new(|req| {
+            assert_eq!("https://influx.example.com:8086/write", req.url);
+            Ok(Response { status: 204, headers: HashMap::new(), body: "".to_string() })
+        }));
+        client.add_host("https://influx.example.com:8086");
+
+        client.write_one(Measurement::new("key"), None).unwrap();
+    }
+
+    #[test]
+    fn test_write_failover() {
+        let urls = Rc::new(RefCell::new(vec![]));
+        let requests = urls.clone();
+
+        let mut client = before(Box::new(move |req| {
+            requests.borrow_mut().push(req.url.to_string());
+
+            match req.url {
+                "http://first:8086/write" => Err("connection refused".to_string()),
+                _ => Ok(Response { status: 204, headers: HashMap::new(), body: "".to_string() })
+            }
+        }));
+        client.add_host("http://first:8086");
+        client.add_host("http://second:8086");
+
+        assert_eq!(1, client.write_one(Measurement::new("key"), None).unwrap().written);
+        assert_eq!(vec!["http://first:8086/write", "http://second:8086/write"], *urls.borrow());
+    }
+
+    #[test]
+    fn test_write_round_robin() {
+        let urls = Rc::new(RefCell::new(vec![]));
+        let requests = urls.clone();
+
+        let mut client = before(Box::new(move |req| {
+            requests.borrow_mut().push(req.url.to_string());
+            Ok(Response { status: 204, headers: HashMap::new(), body: "".to_string() })
+        }));
+        client.add_host("http://first:8086");
+        client.add_host("http://second:8086");
+
+        for _ in 0..3 {
+            client.write_one(Measurement::new("key"), None).unwrap();
+        }
+
+        assert_eq!(vec!["http://first:8086/write", "http://second:8086/write", "http://first:8086/write"], *urls.borrow());
+    }
+
+    #[test]
+    fn test_query_failover_all_hosts_fail() {
+        let count = Rc::new(Cell::new(0));
+        let counter = count.clone();
+
+        let mut client = before(Box::new(move |_| {
+            counter.set(counter.get() + 1);
+            Ok(Response { status: 503, headers: HashMap::new(), body: "Unavailable".to_string() })
+        }));
+        client.add_host("http://first:8086");
+        client.add_host("http://second:8086");
+
+        match client.query("show databases".to_string(), None) {
+            Err(ClientError::Http { status: 503, .. }) => {},
+            other => panic!("unexpected result: {:?}", other)
+        }
+
+        assert_eq!(2, count.get());
+    }
+
+    #[test]
+    fn test_anonymous() {
+        let hurl = MockHurl::new(Box::new(|req| {
+            assert!(req.auth.is_none());
+
+            let query = req.query.as_ref().unwrap();
+            assert!(!query.contains_key("u"));
+            assert!(!query.contains_key("p"));
+
+            Ok(Response { status: 204, headers: HashMap::new(), body: "".to_string() })
+        }));
+
+        let mut client = HttpClient::new(Credentials::anonymous("test"), Box::new(MockSerializer::new()), Box::new(hurl));
+        client.add_host("http://localhost:8086");
+
+        client.write_one(Measurement::new("key"), None).unwrap();
+    }
+
+    #[test]
+    fn test_write_rate_limited() {
+        let mut client = before(Box::new(|_| Ok(Response { status: 204, headers: HashMap::new(), body: "".to_string() })));
+        client.add_host("http://localhost:8086");
+        client.set_rate_limiter(Some(RateLimiter::new(100.0, 1, RateLimitPolicy::Block)));
+
+        let started = Instant::now();
+
+        for _ in 0..5 {
+            client.write_one(Measurement::new("key"), None).unwrap();
+        }
+
+        assert!(started.elapsed() >= Duration::from_millis(40));
+
+        client.set_rate_limiter(Some(RateLimiter::new(1.0, 1, RateLimitPolicy::Fail)));
+        client.write_one(Measurement::new("key"), None).unwrap();
+
+        match client.write_one(Measurement::new("key"), None) {
+            Err(ClientError::RateLimited) => {},
+            other => panic!("unexpected result: {:?}", other)
+        }
+    }
+
+    #[test]
+    fn test_write_omit_timestamps() {
+        let credentials = Credentials {
+            username: "gobwas",
+            password: "1234",
+            database: "test"
+        };
+
+        let hurl = MockHurl::new(Box::new(|req| {
+            assert_eq!(Some("key value=1i".to_string()), req.body);
+            Ok(Response { status: 204, headers: HashMap::new(), body: "".to_string() })
+        }));
+
+        let mut client = HttpClient::new(credentials, Box::new(LineSerializer::new()), Box::new(hurl));
+        client.add_host("http://localhost:8086");
+
+        let mut measurement = Measurement::new("key");
+        measurement.add_field("value", Value::Integer(1));
+        measurement.set_timestamp(1434055562000000000);
+
+        let measurements = [measurement];
+        client.write_many_omit_timestamps(&measurements, None).unwrap();
+
+        assert_eq!(Some(1434055562000000000), measurements[0].timestamp);
+    }
+
+    #[test]
+    fn test_try_write_many() {
+        let credentials = Credentials {
+            username: "gobwas",
+            password: "1234",
+            database: "test"
+        };
+
+        let hurl = MockHurl::new(Box::new(|req| {
+            assert_eq!(Some("a value=1\nc value=3".to_string()), req.body);
+            Ok(Response { status: 204, headers: HashMap::new(), body: "".to_string() })
+        }));
+
+        let mut client = HttpClient::new(credentials, Box::new(LineSerializer::new()), Box::new(hurl));
+        client.add_host("http://localhost:8086");
+
+        let measurements = vec![
+            Measurement::new("a").with_field("value", Value::Float(1.0)),
+            Measurement::new("b").with_field("value", Value::Float(f64::NAN)),
+            Measurement::new("c").with_field("value", Value::Float(3.0))
+        ];
+
+        let (report, skipped) = client.try_write_many(&measurements, None).unwrap();
+
+        assert_eq!(2, report.written);
+        assert_eq!(1, skipped.len());
+
+        match skipped[0] {
+            (1, SerializeError::NonFiniteFloat { ref key, ref field }) => {
+                assert_eq!("b", key);
+                assert_eq!("value", field);
+            },
+            ref other => panic!("unexpected skipped: {:?}", other)
+        }
+    }
+
+    #[test]
+    fn test_write_error_header() {
+        let mut client = before(Box::new(|_| {
+            let mut headers = HashMap::new();
+            headers.insert("X-Influxdb-Error".to_string(), "unable to parse 'key': missing fields".to_string());
+
+            Ok(Response { status: 400, headers: headers, body: "".to_string() })
+        }));
+        client.add_host("http://localhost:8086");
+
+        match client.write_one(Measurement::new("key"), None) {
+            Err(ClientError::Syntax(reason)) => assert_eq!("unable to parse 'key': missing fields", reason),
+            other => panic!("unexpected result: {:?}", other)
+        }
+    }
+
+    #[test]
+    fn test_write_many_empty() {
+        let client = before(Box::new(|_| panic!("request should not be sent")));
+        assert_eq!(WriteReport::empty(), client.write_many(&[], None).unwrap());
+    }
+
+    #[test]
+    fn test_query_stream_into() {
+        let body = "{\"results\":[{\"series\":[{\"name\":\"a\"}]}]}\n{\"results\":[{\"series\":[{\"name\":\"b\"}]}]}\n";
+
+        let mut client = before(Box::new(move |req| {
+            assert_eq!(Some(&"true".to_string()), req.query.as_ref().unwrap().get("chunked"));
+            Ok(Response { status: 200, headers: HashMap::new(), body: body.to_string() })
+        }));
+        client.add_host("http://localhost:8086");
+
+        let mut buf = Vec::new();
+        client.query_stream_into("select * from \"a\", \"b\"".to_string(), None, &mut buf).unwrap();
+
+        assert_eq!(body.as_bytes(), &buf[..]);
+    }
+
+    fn verify_hurl(q: &'static str, db: &'static str, body: &'static str) -> Box<Hurl> {
+        Box::new(MockHurl::new(Box::new(move |req| {
+            match req.method {
+                Method::POST => Ok(Response { status: 204, headers: HashMap::new(), body: "".to_string() }),
+                Method::GET => {
+                    let query = req.query.as_ref().unwrap();
+                    assert_eq!(Some(&q.to_string()), query.get("q"));
+                    assert_eq!(Some(&db.to_string()), query.get("db"));
+                    assert_eq!(Some(&"ns".to_string()), query.get("epoch"));
+                    Ok(Response { status: 200, headers: HashMap::new(), body: body.to_string() })
+                }
+            }
+        })))
+    }
+
+    fn verify_client<'a>(body: &'static str) -> HttpClient<'a> {
+        let credentials = Credentials { username: "gobwas", password: "1234", database: "test" };
+        let hurl = verify_hurl("select * from \"key\" where time = 10 and \"tag\" = 'value'", "test", body);
+
+        let mut client = HttpClient::new(credentials, Box::new(MockSerializer::new()), hurl);
+        client.add_host("http://localhost:8086");
+        client
+    }
+
+    fn verify_measurement<'a>() -> Measurement<'a> {
+        let mut measurement = Measurement::new("key");
+        measurement.add_tag("tag", "value");
+        measurement.set_timestamp(10);
+        measurement
+    }
+
+    #[test]
+    fn test_write_and_verify() {
+        let client = verify_client("{\"results\":[{\"series\":[{\"name\":\"key\",\"columns\":[\"time\",\"tag\"],\"values\":[[10,\"value\"]]}]}]}");
+        assert!(client.write_and_verify(verify_measurement()).is_ok());
+    }
+
+    #[test]
+    fn test_write_and_verify_not_found() {
+        let bodies = vec![
+            "{\"results\":[{}]}",
+            "{\"results\":[{\"series\":[{\"name\":\"other\",\"columns\":[\"time\"],\"values\":[[10]]}]}]}",
+            "{\"results\":[{\"series\":[{\"name\":\"key\",\"columns\":[\"time\",\"tag\"],\"values\":[]}]}]}",
+            "{\"results\":[{\"error\":\"retention policy not found\"}]}",
+            "{\"error\":\"\"}"
+        ];
+
+        for body in bodies {
+            match verify_client(body).write_and_verify(verify_measurement()) {
+                Err(ClientError::Unverified(_)) | Err(ClientError::Unexpected(_)) => {},
+                other => panic!("unexpected result for {}: {:?}", body, other)
+            }
+        }
+    }
+
+    #[test]
+    fn test_write_and_verify_retention_policy() {
+        let body = "{\"results\":[{\"series\":[{\"name\":\"key\",\"columns\":[\"time\",\"tag\"],\"values\":[[10,\"value\"]]}]}]}";
+
+        let credentials = Credentials { username: "gobwas", password: "1234", database: "test" };
+        let hurl = verify_hurl("select * from \"one_week\".\"key\" where time = 10 and \"tag\" = 'value'", "test", body);
+
+        let mut client = HttpClient::new(credentials, Box::new(MockSerializer::new()), hurl);
+        client.add_host("http://localhost:8086");
+        client.set_retention_policy(Some("one_week"));
+
+        assert!(client.write_and_verify(verify_measurement()).is_ok());
+    }
+
+    #[test]
+    fn test_write_and_verify_v2() {
+        let body = "{\"results\":[{\"series\":[{\"name\":\"key\",\"columns\":[\"time\",\"tag\"],\"values\":[[10,\"value\"]]}]}]}";
+
+        let credentials = TokenCredentials { token: "secret", org: Org::Name("myorg"), bucket: "mybucket" };
+        let hurl = verify_hurl("select * from \"key\" where time = 10 and \"tag\" = 'value'", "mybucket", body);
+
+        let mut client = HttpClient::new_v2(credentials, Box::new(MockSerializer::new()), hurl);
+        client.add_host("http://localhost:8086");
+
+        assert!(client.write_and_verify(verify_measurement()).is_ok());
+    }
+
+    #[test]
+    fn test_query_typed() {
+        let mut client = before(Box::new(|_| {
+            let body = "{\"results\":[{\"statement_id\":0,\"series\":[{\"name\":\"databases\",\"columns\":[\"name\"],\"values\":[[\"_internal\"],[\"test\"]]}]}]}";
+            Ok(Response { status: 200, headers: HashMap::new(), body: body.to_string() })
+        }));
+        client.add_host("http://localhost:8086");
+
+        let result = client.query_typed("show databases".to_string(), None).unwrap();
+        let series = &result.results[0].series[0];
+
+        assert_eq!("databases", series.name);
+        assert_eq!(vec!["name"], series.columns);
+        assert_eq!(vec![vec![QueryValue::String("_internal".to_string())], vec![QueryValue::String("test".to_string())]], series.values);
+    }
+
+    #[test]
+    fn test_query_into() {
+        let mut client = before(Box::new(|req| {
+            assert_eq!(Some(&"s".to_string()), req.query.as_ref().unwrap().get("epoch"));
+
+            let body = "{\"results\":[{\"statement_id\":0,\"series\":[{\"name\":\"sut\",\"columns\":[\"time\",\"integer\"],\"values\":[[1434055562,10],[1434055563,-1]]}]}]}";
+            Ok(Response { status: 200, headers: HashMap::new(), body: body.to_string() })
+        }));
+        client.add_host("http://localhost:8086");
+
+        let rows: Vec<BTreeMap<String, i64>> = client.query_into("select integer from sut".to_string(), Some(Precision::Seconds)).unwrap();
+
+        assert_eq!(2, rows.len());
+        assert_eq!((Some(&1434055562), Some(&10)), (rows[0].get("time"), rows[0].get("integer")));
+        assert_eq!((Some(&1434055563), Some(&-1)), (rows[1].get("time"), rows[1].get("integer")));
+    }
+
+    #[test]
+    fn test_ping() {
+        let mut client = before(Box::new(|req| {
+            assert_eq!("http://localhost:8086/ping", req.url);
+
+            let mut headers = HashMap::new();
+            headers.insert("X-Influxdb-Version".to_string(), "1.8.10".to_string());
+
+            Ok(Response { status: 204, headers: headers, body: "".to_string() })
+        }));
+        client.add_host("http://localhost:8086");
+
+        assert_eq!("1.8.10", client.ping().unwrap());
+    }
+
+    #[test]
+    fn test_ping_failed() {
+        let mut client = before(Box::new(|req| match req.url {
+            "http://first:8086/ping" => Err("connection refused".to_string()),
+            _ => Ok(Response { status: 204, headers: HashMap::new(), body: "".to_string() })
+        }));
+        client.add_host("http://first:8086");
+
+        match client.ping() {
+            Err(ClientError::Communication(reason)) => assert_eq!("connection refused", reason),
+            other => panic!("unexpected result: {:?}", other)
+        }
+
+        client.add_host("http://second:8086");
+
+        match client.ping() {
+            Err(ClientError::Unexpected(_)) => {},
+            other => panic!("unexpected result: {:?}", other)
+        }
+    }
+
+    #[test]
+    fn test_query_scalar() {
+        let body = "{\"results\":[{\"statement_id\":0,\"series\":[{\"name\":\"sut\",\"columns\":[\"time\",\"count\"],\"values\":[[\"1970-01-01T00:00:00Z\",100]]}]}]}";
+        let hurl = ::hurl::mock::MockHurl::new(Response { status: 200, headers: HashMap::new(), body: body.to_string() });
+        let requests = hurl.requests();
+
+        let mut client = HttpClient::new(Credentials::anonymous("test"), Box::new(LineSerializer::new()), Box::new(hurl));
+        client.add_host("http://localhost:8086");
+
+        assert_eq!(Some(100u64), client.query_scalar("select count(value) from sut".to_string()).unwrap());
+        assert_eq!(Some(&"select count(value) from sut".to_string()), requests.borrow()[0].query.get("q"));
+    }
+
+    #[test]
+    fn test_result_parser() {
+        struct EmptyParser;
+
+        impl ResultParser for EmptyParser {
+            fn parse(&self, body: &str) -> Result<QueryResult, String> {
+                assert_eq!("{\"results\":[]}", body);
+                Ok(QueryResult { results: vec![StatementResult { statement_id: Some(7), series: vec![], error: None }] })
+            }
+        }
+
+        let mut client = before(Box::new(|_| Ok(Response { status: 200, headers: HashMap::new(), body: "{\"results\":[]}".to_string() })));
+        client.add_host("http://localhost:8086");
+
+        assert!(client.query_typed("show databases".to_string(), None).unwrap().results.is_empty());
+
+        client.set_result_parser(Box::new(EmptyParser));
+        assert_eq!(Some(7), client.query_typed("show databases".to_string(), None).unwrap().results[0].statement_id);
+    }
+
+    #[test]
+    fn test_status_errors() {
+        let mut client = before(Box::new(|req| {
+            let mut headers = HashMap::new();
+            headers.insert("X-Influxdb-Error".to_string(), "database not found: \"test\"".to_string());
+
+            match req.url {
+                "http://localhost:8086/write" => Ok(Response { status: 404, headers: headers, body: "".to_string() }),
+                _ => Ok(Response { status: 400, headers: headers, body: "".to_string() })
+            }
+        }));
+        client.add_host("http://localhost:8086");
+
+        match client.write_one(Measurement::new("key"), None) {
+            Err(ClientError::Http { status, reason, .. }) => {
+                assert_eq!(404, status);
+                assert_eq!("database not found: \"test\"", reason);
+            },
+            other => panic!("unexpected result: {:?}", other)
+        }
+
+        match client.query("show databases".to_string(), None) {
+            Err(ClientError::Syntax(_)) => {},
+            other => panic!("unexpected result: {:?}", other)
+        }
+    }
+
+    #[test]
+    fn test_status_error_body() {
+        let mut client = before(Box::new(|_| {
+            let mut headers = HashMap::new();
+            headers.insert("X-Influxdb-Error".to_string(), "partial write".to_string());
+
+            let body = "{\"error\":\"partial write: field type conflict: input field \\\"value\\\" on measurement \\\"key\\\" is type integer, already exists as type float dropped=1\"}";
+            Ok(Response { status: 422, headers: headers, body: body.to_string() })
+        }));
+        client.add_host("http://localhost:8086");
+
+        match client.write_one(Measurement::new("key"), None) {
+            Err(ClientError::Http { status, reason, body }) => {
+                assert_eq!(422, status);
+                assert_eq!("partial write", reason);
+                assert!(body.starts_with("{\"error\":\"partial write: field type conflict"), "body: {}", body);
+                assert!(body.ends_with("dropped=1\"}"), "body: {}", body);
+            },
+            other => panic!("unexpected result: {:?}", other)
+        }
+    }
+
+    #[test]
+    fn test_query_with_params() {
+        let params = Rc::new(RefCell::new(vec![]));
+        let requests = params.clone();
+
+        let mut client = before(Box::new(move |req| {
+            let query = req.query.as_ref().unwrap();
+            assert_eq!(Some(&"select * from cpu where host = $host and load > $min".to_string()), query.get("q"));
+            requests.borrow_mut().push(query.get("params").cloned());
+
+            Ok(Response { status: 200, headers: HashMap::new(), body: "{\"results\":[]}".to_string() })
+        }));
+        client.add_host("http://localhost:8086");
+
+        let mut bound = BTreeMap::new();
+        bound.insert("host", Value::String("server \"01\""));
+        bound.insert("min", Value::Float(0.5));
+        bound.insert("limit", Value::Integer(10));
+
+        client.query_with_params("select * from cpu where host = $host and load > $min".to_string(), &bound, None).unwrap();
+        client.query("select * from cpu where host = $host and load > $min".to_string(), None).unwrap();
+
+        let expected = vec![Some("{\"host\":\"server \\\"01\\\"\",\"limit\":10,\"min\":0.5}".to_string()), None];
+        assert_eq!(expected, *params.borrow());
+    }
+
+    #[test]
+    fn test_query_epoch() {
+        let epochs = Rc::new(RefCell::new(vec![]));
+        let requests = epochs.clone();
+
+        let mut client = before(Box::new(move |req| {
+            requests.borrow_mut().push(req.query.as_ref().unwrap().get("epoch").cloned());
+            Ok(Response { status: 200, headers: HashMap::new(), body: "{\"results\":[]}".to_string() })
+        }));
+        client.add_host("http://localhost:8086");
+
+        client.query("show databases".to_string(), None).unwrap();
+        client.query("show databases".to_string(), Some(Precision::Milliseconds)).unwrap();
+        client.query_typed("show databases".to_string(), Some(Precision::Nanoseconds)).unwrap();
+
+        assert_eq!(vec![None, Some("ms".to_string()), Some("ns".to_string())], *epochs.borrow());
+    }
+
+    #[test]
+    fn test_query_html_response() {
+        let mut client = before(Box::new(|_| {
+            let mut headers = HashMap::new();
+            headers.insert("Content-Type".to_string(), "text/html".to_string());
+
+            Ok(Response { status: 200, headers: headers, body: "<html><body>Bad Gateway</body></html>".to_string() })
+        }));
+        client.add_host("http://localhost:8086");
+
+        match client.query("show databases".to_string(), None) {
+            Err(ClientError::UnexpectedResponse { content_type, snippet }) => {
+                assert_eq!(Some("text/html".to_string()), content_type);
+                assert_eq!("<html><body>Bad Gateway</body></html>", snippet);
+            },
+            other => panic!("unexpected result: {:?}", other)
+        }
+    }
+
+    #[test]
+    fn test_query_html_response_without_content_type() {
+        let mut client = before(Box::new(|_| Ok(Response { status: 200, headers: HashMap::new(), body: "\n<html></html>".to_string() })));
+        client.add_host("http://localhost:8086");
+
+        match client.query("show databases".to_string(), None) {
+            Err(ClientError::UnexpectedResponse { content_type: None, .. }) => {},
+            other => panic!("unexpected result: {:?}", other)
+        }
+    }
+
+    #[test]
+    fn test_validate_timestamps() {
+        let hurl = MockHurl::new(Box::new(|_| Ok(Response { status: 204, headers: HashMap::new(), body: "".to_string() })));
+        let credentials = TokenCredentials { token: "secret", org: Org::Name("myorg"), bucket: "mybucket" };
+        let mut client = HttpClient::new_v2(credentials, Box::new(MockSerializer::new()), Box::new(hurl));
+        client.add_host("http://localhost:8086");
+        client.set_validate_timestamps(true);
+
+        let mut measurement = Measurement::new("key");
+        measurement.set_timestamp(1434055562);
+
+        match client.write_one(measurement, None) {
+            Err(ClientError::ImplausibleTimestamp(1434055562)) => {},
+            other => panic!("unexpected result: {:?}", other)
+        }
+
+        let mut measurement = Measurement::new("key");
+        measurement.set_timestamp(1434055562);
+        assert!(client.write_one(measurement, Some(Precision::Seconds)).is_ok());
+
+        let mut measurement = Measurement::new("key");
+        measurement.set_timestamp(1434055562000000000);
+        assert!(client.write_one(measurement, None).is_ok());
+    }
+
+    #[test]
+    fn test_validate_timestamps_v1() {
+        let mut client = before(Box::new(|_| Ok(Response { status: 204, headers: HashMap::new(), body: "".to_string() })));
+        client.add_host("http://localhost:8086");
+        client.set_validate_timestamps(true);
+
+        // 1.x writes are not checked, e.g. of points before 1973
+        let mut measurement = Measurement::new("key");
+        measurement.set_timestamp(1434055562);
+        assert_eq!(1, client.write_one(measurement, None).unwrap().written);
+
+        let mut measurement = Measurement::new("key");
+        measurement.set_timestamp(31536000);
+        assert_eq!(1, client.write_one(measurement, Some(Precision::Seconds)).unwrap().written);
+    }
+
+    #[test]
+    fn test_validate_measurements() {
+        let mut client = before(Box::new(|_| panic!("invalid measurement should not be sent")));
+        client.add_host("http://localhost:8086");
+        client.set_validate_measurements(true);
+
+        match client.write_many(&[Measurement::new("key").with_field("f", 1i64), Measurement::new("key")], None) {
+            Err(ClientError::Invalid(ValidationError::NoFields)) => {},
+            other => panic!("unexpected result: {:?}", other)
+        }
+
+        match client.write_one(Measurement::new("").with_field("f", 1i64), None) {
+            Err(ClientError::Invalid(ValidationError::EmptyKey)) => {},
+            other => panic!("unexpected result: {:?}", other)
+        }
+    }
+
+    #[test]
+    fn test_delete() {
+        let mut client = before(Box::new(|req| {
+            assert_eq!(Some(&"delete from \"key\" where \"host\" = 'it\\'s' and \"path\" = 'C:\\\\dir'".to_string()), req.query.as_ref().unwrap().get("q"));
+            Ok(Response { status: 200, headers: HashMap::new(), body: "{\"results\":[{}]}".to_string() })
+        }));
+        client.add_host("http://localhost:8086");
+
+        assert!(client.delete("key", &[("host", "it's"), ("path", "C:\\dir")]).is_ok());
+    }
+
+    struct MockSigner;
+
+    impl Signer for MockSigner {
+        fn sign(&self, method: &Method, path: &str, body: Option<&str>) -> Vec<(String, String)> {
+            vec![("X-Signature".to_string(), format!("{:?} {} {}", method, path, body.unwrap_or("")))]
+        }
+    }
+
+    #[test]
+    fn test_signer() {
+        let mut client = before(Box::new(|req| {
+            assert_eq!(Some(&"POST /write serialized".to_string()), req.headers.get("X-Signature"));
+            Ok(Response { status: 204, headers: HashMap::new(), body: "".to_string() })
+        }));
+        client.add_host("http://localhost:8086");
+        client.set_signer(Box::new(MockSigner));
+
+        assert!(client.write_one(Measurement::new("key"), None).is_ok());
+    }
+}
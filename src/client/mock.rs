@@ -0,0 +1,39 @@
+use ::serializer::Serializer;
+use ::serializer::line::LineSerializer;
+use ::client::{Client, ClientError, Precision, ClientReadResult, ClientWriteResult, WriteReport};
+use ::measurement::Measurement;
+use std::cell::RefCell;
+
+// Client, which records written measurements as lines, for tests of the client wrappers.
+pub struct MockClient {
+    pub lines: RefCell<Vec<String>>
+}
+
+impl MockClient {
+    pub fn new() -> MockClient {
+        MockClient {
+            lines: RefCell::new(vec![])
+        }
+    }
+}
+
+impl Client for MockClient {
+    fn query(&self, _: String, _: Option<Precision>) -> ClientReadResult {
+        Err(ClientError::CouldNotComplete("querying is not supported by MockClient".to_string()))
+    }
+
+    fn write_one(&self, measurement: Measurement, precision: Option<Precision>) -> ClientWriteResult {
+        self.write_many(&[measurement], precision)
+    }
+
+    fn write_many(&self, measurements: &[Measurement], _: Option<Precision>) -> ClientWriteResult {
+        let serializer = LineSerializer::new();
+
+        for (i, measurement) in measurements.iter().enumerate() {
+            let line = try!(serializer.try_serialize(measurement).map_err(|err| ClientError::Serialize(i, err)));
+            self.lines.borrow_mut().push(line);
+        }
+
+        Ok(WriteReport { written: measurements.len(), resume_index: None, points_written: None })
+    }
+}
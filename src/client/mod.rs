@@ -1,14 +1,50 @@
-use ::measurement::Measurement;
+use ::measurement::{Measurement, ValidationError};
+use ::serializer::SerializeError;
+use ::query::{QueryResult, ResultParser, JsonParser};
+use rustc_serialize::Decodable;
 use std::io;
 
 #[cfg(feature = "http")]
 pub mod http;
 pub mod udp;
+pub mod batch;
+pub mod routing;
+pub mod dedup;
+pub mod backoff;
+pub mod rate_limit;
+#[cfg(test)]
+mod mock;
 
 pub trait Client {
     fn write_many(&self, &[Measurement], Option<Precision>) -> ClientWriteResult;
     fn write_one(&self, Measurement, Option<Precision>) -> ClientWriteResult;
     fn query(&self, String, Option<Precision>) -> ClientReadResult;
+
+    /// Returns parser of the query results. Defaults to `JsonParser`.
+    fn result_parser(&self) -> &ResultParser {
+        &JsonParser
+    }
+
+    /// Queries and parses the result with `result_parser`, instead of returning the raw JSON.
+    fn query_typed(&self, q: String, epoch: Option<Precision>) -> Result<QueryResult, ClientError> {
+        let raw = try!(self.query(q, epoch));
+        self.result_parser().parse(&raw).map_err(ClientError::Unexpected)
+    }
+
+    /// Queries and decodes rows of all the series into values, e.g. structs with fields named
+    /// as the columns. Column `time` is a RFC3339 string, or an integer if `epoch` is given.
+    fn query_into<T: Decodable>(&self, q: String, epoch: Option<Precision>) -> Result<Vec<T>, ClientError> where Self: Sized {
+        let result = try!(self.query_typed(q, epoch));
+        result.rows().map_err(ClientError::Unexpected)
+    }
+
+    /// Queries and decodes the single value of the first row of the first series, e.g. of
+    /// `select count(*)`. Returns `None` if nothing is found, and fails if the result is not
+    /// a single value.
+    fn query_scalar<T: Decodable>(&self, q: String) -> Result<Option<T>, ClientError> where Self: Sized {
+        let result = try!(self.query_typed(q, None));
+        result.scalar().map_err(ClientError::Unexpected)
+    }
 }
 
 pub struct Credentials<'a> {
@@ -17,6 +53,67 @@ pub struct Credentials<'a> {
     pub database: &'a str
 }
 
+impl<'a> Credentials<'a> {
+    /// Constructs credentials without username and password, e.g. for local InfluxDB running
+    /// without authentication. Requests are then sent without authorization.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use influent::client::Credentials;
+    ///
+    /// let credentials = Credentials::anonymous("mydb");
+    ///
+    /// assert!(credentials.is_anonymous());
+    /// ```
+    pub fn anonymous(database: &'a str) -> Credentials<'a> {
+        Credentials {
+            username: "",
+            password: "",
+            database: database
+        }
+    }
+
+    /// Returns whether credentials have no username.
+    pub fn is_anonymous(&self) -> bool {
+        self.username.is_empty()
+    }
+}
+
+/// Organization of InfluxDB 2.x, given by name or by ID.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Org<'a> {
+    Name(&'a str),
+    Id(&'a str)
+}
+
+impl<'a> Org<'a> {
+    /// Returns name of the query parameter, the organization is sent in.
+    pub fn param(&self) -> &'static str {
+        match *self {
+            Org::Name(_) => "org",
+            Org::Id(_) => "orgID"
+        }
+    }
+
+    /// Returns name or ID of the organization.
+    pub fn value(&self) -> &'a str {
+        match *self {
+            Org::Name(name) => name,
+            Org::Id(id) => id
+        }
+    }
+}
+
+/// Credentials of InfluxDB 2.x, which authorizes requests with the API token and writes
+/// to the bucket of the organization.
+pub struct TokenCredentials<'a> {
+    pub token: &'a str,
+    pub org: Org<'a>,
+    pub bucket: &'a str
+}
+
+#[derive(Debug, Clone, Copy)]
 pub enum Precision {
     Nanoseconds,
     Microseconds,
@@ -26,6 +123,20 @@ pub enum Precision {
     Hours
 }
 
+impl Precision {
+    /// Converts timestamp of this precision to seconds.
+    pub fn to_seconds(&self, timestamp: i64) -> i64 {
+        match *self {
+            Precision::Nanoseconds  => timestamp / 1000000000,
+            Precision::Microseconds => timestamp / 1000000,
+            Precision::Milliseconds => timestamp / 1000,
+            Precision::Seconds      => timestamp,
+            Precision::Minutes      => timestamp.saturating_mul(60),
+            Precision::Hours        => timestamp.saturating_mul(3600)
+        }
+    }
+}
+
 impl ToString for Precision {
     fn to_string(&self) -> String {
         let s = match (*self) {
@@ -41,20 +152,118 @@ impl ToString for Precision {
     }
 }
 
-pub type ClientWriteResult = Result<(), ClientError>;
+/// Number of cluster nodes, which must confirm the write of InfluxDB Enterprise.
+#[derive(Debug, Clone, Copy)]
+pub enum Consistency {
+    Any,
+    One,
+    Quorum,
+    All
+}
+
+impl ToString for Consistency {
+    fn to_string(&self) -> String {
+        let s = match *self {
+            Consistency::Any    => "any",
+            Consistency::One    => "one",
+            Consistency::Quorum => "quorum",
+            Consistency::All    => "all"
+        };
+
+        s.to_string()
+    }
+}
+
+pub type ClientWriteResult = Result<WriteReport, ClientError>;
 
 // TODO: here parsing json?
 pub type ClientReadResult = Result<String, ClientError>;
 
+/// Report of the write.
+#[derive(Debug, PartialEq)]
+pub struct WriteReport {
+    /// Number of measurements written.
+    pub written: usize,
+    /// Index of the first measurement not known to be persisted, if the write was interrupted.
+    pub resume_index: Option<usize>,
+    /// Number of points written as reported by the server, if it reports one.
+    pub points_written: Option<usize>
+}
+
+impl WriteReport {
+    /// Constructs report of the write, where nothing was written.
+    pub fn empty() -> WriteReport {
+        WriteReport {
+            written: 0,
+            resume_index: None,
+            points_written: None
+        }
+    }
+}
+
 #[derive(Debug)]
 pub enum ClientError {
     CouldNotComplete(String),
     Communication(String),
     Syntax(String),
     Unexpected(String),
+    /// Response has unexpected status, e.g. 404 for missing database or 5xx on server failure.
+    Http {
+        status: u16,
+        /// `X-Influxdb-Error` header or body of the response.
+        reason: String,
+        /// Body of the response, e.g. `{"error":"partial write: ..."}`.
+        body: String
+    },
+    /// Response is not InfluxDB's JSON, e.g. an HTML error page of misconfigured proxy.
+    UnexpectedResponse {
+        content_type: Option<String>,
+        snippet: String
+    },
+    /// Timestamp of InfluxDB 2.x write is of obviously wrong scale for the write precision.
+    ImplausibleTimestamp(i64),
+    /// Measurement name, tag or field name contains newline, which could not be escaped.
+    InvalidKey(String),
+    /// Measurement is rejected by `Measurement::validate`.
+    Invalid(ValidationError),
+    /// Serialized measurement of given length does not fit into a single UDP packet.
+    MeasurementTooLarge(usize),
+    /// Measurement at the index could not be serialized, e.g. it has a NaN field.
+    /// Nothing is written then.
+    Serialize(usize, SerializeError),
+    /// Request timed out, see `HttpClient::set_timeout`.
+    Timeout,
+    /// Write would exceed the rate limit.
+    RateLimited,
+    /// Tag (name, value) is not in the allowed values of the tag.
+    DisallowedTagValue(String, String),
+    /// Written measurement was not found on verification.
+    Unverified(String),
+    /// Some batches were persisted before the error; resume from `WriteReport::resume_index`.
+    Interrupted(WriteReport, Box<ClientError>),
     Unknown
 }
 
+// Line protocol has no escaping for newline in names, so it would split the line
+// and corrupt the whole batch. Serializer rejects such names too, but checking them up front
+// fails the whole write before anything is sent, as the other validations do.
+fn check_keys(measurements: &[Measurement]) -> Result<(), ClientError> {
+    for measurement in measurements {
+        let names = Some(&measurement.key[..]).into_iter()
+            .chain(measurement.tags.keys().map(|tag| *tag))
+            .chain(measurement.tags.values().map(|value| *value))
+            .chain(measurement.fields.keys().map(|field| *field));
+
+        for name in names {
+            if name.contains('\n') {
+                return Err(ClientError::InvalidKey(name.to_string()));
+            }
+        }
+    }
+
+    Ok(())
+}
+
 impl From<io::Error> for ClientError {
     fn from(e: io::Error) -> Self {
         ClientError::Communication(format!("{}", e))
@@ -0,0 +1,139 @@
+use ::client::ClientError;
+use std::time::{Duration, Instant};
+use std::cell::Cell;
+use std::thread;
+
+/// Behavior of the rate limiter, when the rate is exceeded.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum RateLimitPolicy {
+    /// Blocks until the write is allowed.
+    Block,
+    /// Fails with `ClientError::RateLimited`.
+    Fail
+}
+
+/// Token bucket limiter of the write requests rate, e.g. to respect rate limits of InfluxDB Cloud.
+#[derive(Debug, Clone)]
+pub struct RateLimiter {
+    rate: f64,
+    burst: f64,
+    policy: RateLimitPolicy,
+    tokens: Cell<f64>,
+    updated: Cell<Instant>
+}
+
+impl RateLimiter {
+    /// Constructs a new `RateLimiter`, which allows `rate` requests per second on average,
+    /// and up to `burst` requests at once.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `rate` is not positive, e.g. zero or NaN, or `burst` is zero, as no request
+    /// would be ever allowed.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use influent::client::rate_limit::{RateLimiter, RateLimitPolicy};
+    ///
+    /// let limiter = RateLimiter::new(10.0, 1, RateLimitPolicy::Fail);
+    ///
+    /// assert!(limiter.acquire().is_ok());
+    /// assert!(limiter.acquire().is_err());
+    /// ```
+    pub fn new(rate: f64, burst: u32, policy: RateLimitPolicy) -> RateLimiter {
+        assert!(rate > 0.0, "rate must be positive, got {}", rate);
+        assert!(burst > 0, "burst must be positive");
+
+        RateLimiter {
+            rate: rate,
+            burst: burst as f64,
+            policy: policy,
+            tokens: Cell::new(burst as f64),
+            updated: Cell::new(Instant::now())
+        }
+    }
+
+    /// Takes a token for the request, blocking or failing according to the policy,
+    /// if there is none.
+    pub fn acquire(&self) -> Result<(), ClientError> {
+        let now = Instant::now();
+        let elapsed = now.duration_since(self.updated.get());
+        let elapsed = elapsed.as_secs() as f64 + elapsed.subsec_nanos() as f64 / 1e9;
+
+        let tokens = (self.tokens.get() + elapsed * self.rate).min(self.burst);
+        self.updated.set(now);
+
+        if tokens >= 1.0 {
+            self.tokens.set(tokens - 1.0);
+            return Ok(());
+        }
+
+        match self.policy {
+            RateLimitPolicy::Fail => {
+                self.tokens.set(tokens);
+                Err(ClientError::RateLimited)
+            },
+            RateLimitPolicy::Block => {
+                let wait = (1.0 - tokens) / self.rate;
+                thread::sleep(Duration::new(wait as u64, (wait.fract() * 1e9) as u32));
+
+                // the token accrued while waiting is taken right away
+                self.tokens.set(0.0);
+                self.updated.set(Instant::now());
+                Ok(())
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{RateLimiter, RateLimitPolicy};
+    use ::client::ClientError;
+    use std::time::{Duration, Instant};
+    use std::f64;
+
+    #[test]
+    fn test_rate_limiter_block() {
+        let limiter = RateLimiter::new(100.0, 1, RateLimitPolicy::Block);
+        let started = Instant::now();
+
+        for _ in 0..5 {
+            limiter.acquire().unwrap();
+        }
+
+        assert!(started.elapsed() >= Duration::from_millis(40));
+    }
+
+    #[test]
+    #[should_panic(expected = "rate must be positive")]
+    fn test_rate_limiter_zero_rate() {
+        RateLimiter::new(0.0, 1, RateLimitPolicy::Block);
+    }
+
+    #[test]
+    #[should_panic(expected = "rate must be positive")]
+    fn test_rate_limiter_nan_rate() {
+        RateLimiter::new(f64::NAN, 1, RateLimitPolicy::Block);
+    }
+
+    #[test]
+    #[should_panic(expected = "burst must be positive")]
+    fn test_rate_limiter_zero_burst() {
+        RateLimiter::new(1.0, 0, RateLimitPolicy::Fail);
+    }
+
+    #[test]
+    fn test_rate_limiter_fail() {
+        let limiter = RateLimiter::new(1.0, 2, RateLimitPolicy::Fail);
+
+        limiter.acquire().unwrap();
+        limiter.acquire().unwrap();
+
+        match limiter.acquire() {
+            Err(ClientError::RateLimited) => {},
+            other => panic!("unexpected result: {:?}", other)
+        }
+    }
+}
@@ -0,0 +1,149 @@
+use ::measurement::Measurement;
+use ::client::{Precision, Client, ClientError, ClientReadResult, ClientWriteResult, WriteReport};
+
+/// Client, which routes measurements to the underlying clients by measurement key,
+/// e.g. for deployments sharding metrics into different databases.
+pub struct RoutingClient<'c> {
+    clients: Vec<Box<Client + 'c>>,
+    route: Box<Fn(&str) -> usize + 'c>
+}
+
+impl<'c> RoutingClient<'c> {
+    /// Constructs a new `RoutingClient`, where `route` maps measurement key to the index of
+    /// the client in `clients`.
+    pub fn new(clients: Vec<Box<Client + 'c>>, route: Box<Fn(&str) -> usize + 'c>) -> RoutingClient<'c> {
+        RoutingClient {
+            clients: clients,
+            route: route
+        }
+    }
+
+    /// Constructs a new `RoutingClient`, which routes measurement to the client of the first
+    /// matching key prefix, or to the `default` client, if none matches.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use influent::create_udp_client;
+    /// use influent::client::routing::RoutingClient;
+    ///
+    /// let system = create_udp_client(vec!["127.0.0.1:8089"]);
+    /// let app = create_udp_client(vec!["127.0.0.1:8090"]);
+    ///
+    /// let client = RoutingClient::with_prefixes(vec![("cpu.", Box::new(system))], Box::new(app));
+    /// ```
+    pub fn with_prefixes(routes: Vec<(&'c str, Box<Client + 'c>)>, default: Box<Client + 'c>) -> RoutingClient<'c> {
+        let mut prefixes = vec![];
+        let mut clients = vec![];
+
+        for (prefix, client) in routes {
+            prefixes.push(prefix);
+            clients.push(client);
+        }
+        clients.push(default);
+
+        RoutingClient::new(clients, Box::new(move |key: &str| {
+            prefixes.iter().position(|prefix| key.starts_with(prefix)).unwrap_or(prefixes.len())
+        }))
+    }
+
+    fn get_client(&self, i: usize) -> &Client {
+        match self.clients.get(i) {
+            Some(client) => &**client,
+            None => panic!("Could not get client #{}", i)
+        }
+    }
+}
+
+impl<'c> Client for RoutingClient<'c> {
+    fn query(&self, _: String, _: Option<Precision>) -> ClientReadResult {
+        Err(ClientError::CouldNotComplete("querying is not supported by routing client".to_string()))
+    }
+
+    fn write_one(&self, measurement: Measurement, precision: Option<Precision>) -> ClientWriteResult {
+        self.write_many(&[measurement], precision)
+    }
+
+    fn write_many(&self, measurements: &[Measurement], precision: Option<Precision>) -> ClientWriteResult {
+        let mut written = 0;
+
+        // write runs of measurements with the same route to keep the order
+        while written < measurements.len() {
+            let i = (self.route)(&measurements[written].key);
+            let len = measurements[written..].iter()
+                .take_while(|m| (self.route)(&m.key) == i)
+                .count();
+
+            match self.get_client(i).write_many(&measurements[written..written + len], precision) {
+                Ok(_) => written += len,
+                Err(ClientError::Interrupted(report, err)) => {
+                    let written = written + report.written;
+                    return Err(ClientError::Interrupted(WriteReport { written: written, resume_index: Some(written), points_written: None }, err));
+                },
+                Err(err) if written > 0 => {
+                    return Err(ClientError::Interrupted(WriteReport { written: written, resume_index: Some(written), points_written: None }, Box::new(err)));
+                },
+                Err(err) => return Err(err)
+            }
+        }
+
+        Ok(WriteReport { written: written, resume_index: None, points_written: None })
+    }
+}
+
+#[cfg(all(test, feature = "http"))]
+mod tests {
+    use ::client::{Client, Credentials};
+    use ::client::http::HttpClient;
+    use ::serializer::line::LineSerializer;
+    use ::hurl::{Hurl, Request, Response, HurlResult};
+    use ::measurement::Measurement;
+    use super::RoutingClient;
+    use std::collections::HashMap;
+    use std::cell::RefCell;
+    use std::rc::Rc;
+
+    struct MockHurl {
+        requests: Rc<RefCell<Vec<String>>>
+    }
+
+    impl Hurl for MockHurl {
+        fn request(&self, req: Request) -> HurlResult {
+            let db = req.query.as_ref().unwrap().get("db").unwrap().clone();
+            self.requests.borrow_mut().push(format!("{} db={} {}", req.url, db, req.body.unwrap()));
+
+            Ok(Response { status: 204, headers: HashMap::new(), body: "".to_string() })
+        }
+    }
+
+    fn client<'a>(database: &'a str, requests: Rc<RefCell<Vec<String>>>) -> Box<Client + 'a> {
+        let credentials = Credentials {
+            username: "gobwas",
+            password: "1234",
+            database: database
+        };
+
+        let mut client = HttpClient::new(credentials, Box::new(LineSerializer::new()), Box::new(MockHurl { requests: requests }));
+        client.add_host("http://localhost:8086");
+
+        Box::new(client)
+    }
+
+    #[test]
+    fn test_routing_by_prefix() {
+        let requests = Rc::new(RefCell::new(vec![]));
+        let client = RoutingClient::with_prefixes(vec![("cpu.", client("system", requests.clone()))], client("app", requests.clone()));
+
+        let measurements: Vec<Measurement> = vec!["cpu.load", "cpu.idle", "requests", "cpu.load"].into_iter()
+            .map(|key| Measurement::new(key).with_field("v", 1i64))
+            .collect();
+        let report = client.write_many(&measurements, None).unwrap();
+
+        assert_eq!(4, report.written);
+        assert_eq!(vec![
+            "http://localhost:8086/write db=system cpu.load v=1i\ncpu.idle v=1i",
+            "http://localhost:8086/write db=app requests v=1i",
+            "http://localhost:8086/write db=system cpu.load v=1i"
+        ], *requests.borrow());
+    }
+}
@@ -1,9 +1,16 @@
 use ::measurement::Measurement;
 use ::serializer::Serializer;
-use ::client::{Precision, Client, Credentials, ClientError, ClientReadResult, ClientWriteResult};
-use std::net::{UdpSocket, ToSocketAddrs};
+use ::client::{Precision, Client, Credentials, ClientError, ClientReadResult, ClientWriteResult, WriteReport, check_keys};
+use ::dns::DnsCache;
+use std::net::{UdpSocket, SocketAddr};
+use std::time::Duration;
+use std::io;
+
+pub use ::dns::{Resolver, SystemResolver};
 
 const MAX_BATCH: u16 = 5000;
+// maximum payload of UDP over IPv4
+const MAX_UDP_PACKET_LEN: usize = 65507;
 
 pub enum WriteStatus {
     Success,
@@ -21,7 +28,9 @@ pub struct Options {
 
 pub struct UdpClient<'a> {
     serializer: Box<Serializer>,
+    dns: DnsCache,
     hosts: Vec<&'a str>,
+    max_packet_len: usize,
     pub max_batch: u16
 }
 
@@ -29,11 +38,34 @@ impl<'a> UdpClient<'a> {
     pub fn new(serializer: Box<Serializer>) -> Self {
         UdpClient {
             serializer: serializer,
+            dns: DnsCache::new(),
             hosts: vec![],
+            max_packet_len: MAX_UDP_PACKET_LEN,
             max_batch: MAX_BATCH
         }
     }
 
+    /// Sets resolver of the host address.
+    pub fn set_resolver(&mut self, resolver: Box<Resolver>) {
+        self.dns.set_resolver(resolver);
+    }
+
+    /// Sets for how long resolved host address is reused. By default host is resolved on every
+    /// write; caching it avoids that, while still following address changes after the `ttl`.
+    pub fn set_dns_ttl(&mut self, ttl: Option<Duration>) {
+        self.dns.set_ttl(ttl);
+    }
+
+    /// Sets maximum length of the packet, e.g. to the path MTU, so packets are not fragmented.
+    /// Defaults to the maximum UDP payload over IPv4.
+    pub fn set_max_packet_len(&mut self, len: usize) {
+        self.max_packet_len = len;
+    }
+
+    fn resolve(&self) -> io::Result<SocketAddr> {
+        self.dns.resolve(self.get_host())
+    }
+
     pub fn add_host(&mut self, host: &'a str) {
         self.hosts.push(host);
     }
@@ -56,42 +88,90 @@ impl<'a> Client for UdpClient<'a> {
     }
 
     fn write_many(&self, measurements: &[Measurement], _: Option<Precision>) -> ClientWriteResult {
+        if measurements.is_empty() {
+            return Ok(WriteReport::empty());
+        }
+
+        try!(check_keys(measurements));
+
+        // all measurements are serialized up front, so nothing is sent if any of them fails
+        let mut lines = vec![];
+
+        for (i, measurement) in measurements.iter().enumerate() {
+            match self.serializer.try_serialize(measurement) {
+                // skipped by the serializer itself, e.g. measurement without fields
+                Ok(ref line) if line.is_empty() => {},
+                Ok(line) => lines.push((i, line)),
+                Err(err) => return Err(ClientError::Serialize(i, err))
+            }
+        }
+
         let socket = try!(UdpSocket::bind("0.0.0.0:0"));
-        let addr = self.get_host().to_socket_addrs().unwrap().last().unwrap();
+        let addr = try!(self.resolve());
+
+        // number of measurements in the sent packets
+        let mut sent = 0;
 
-        for chunk in measurements.chunks(self.max_batch as usize) {
+        for chunk in lines.chunks(self.max_batch as usize) {
             let mut bytes = Vec::new();
-            const MAX_UDP_PACKET_LEN: usize = 65535;
+            let mut packed = 0;
 
-            for measurement in chunk {
-                let line = self.serializer.serialize(measurement);
+            for &(i, ref line) in chunk {
                 let line = line.as_bytes();
-                if line.len() + bytes.len() < MAX_UDP_PACKET_LEN {
-                    bytes.extend_from_slice(&line[..]);
-                    bytes.push(b'\n');
-                } else {
+
+                // unlike a batch, a single measurement could not be split,
+                // so the preceding ones are sent and the write is interrupted
+                if line.len() + 1 > self.max_packet_len {
+                    if !bytes.is_empty() {
+                        try!(socket.send_to(&bytes[..], addr));
+                        sent += packed;
+                    }
+
+                    let err = ClientError::MeasurementTooLarge(line.len());
+
+                    if sent > 0 {
+                        return Err(ClientError::Interrupted(WriteReport { written: sent, resume_index: Some(i), points_written: None }, Box::new(err)));
+                    }
+
+                    return Err(err);
+                }
+
+                if line.len() + bytes.len() >= self.max_packet_len {
                     try!(socket.send_to(&bytes[..], addr));
                     bytes.clear();
-                    bytes.extend_from_slice(&line[..]);
-                    bytes.push(b'\n');
+                    sent += packed;
+                    packed = 0;
                 }
+
+                bytes.extend_from_slice(&line[..]);
+                bytes.push(b'\n');
+                packed += 1;
             }
             if !bytes.is_empty() {
                 try!(socket.send_to(&bytes[..], addr));
+                sent += packed;
             }
         }
 
-        Ok(())
+        Ok(WriteReport { written: sent, resume_index: None, points_written: None })
     }
 }
 
 #[cfg(test)]
 mod tests {
     use ::serializer::line::LineSerializer;
-    use ::client::{Client};
-    use super::UdpClient;
+    use ::client::{Client, ClientError};
+    use ::serializer::SerializeError;
+    use super::{UdpClient, Resolver};
     use ::client::Precision;
     use ::measurement::{Measurement,self};
+    use std::net::{SocketAddr, UdpSocket};
+    use std::time::Duration;
+    use std::thread;
+    use std::rc::Rc;
+    use std::cell::Cell;
+    use std::io;
+    use std::f64;
 
     #[test]
     fn test_write_one() {
@@ -109,4 +189,114 @@ mod tests {
         client.add_host("127.0.0.1:8089");
         client.write_many(&[Measurement::new("kek")], Some(Precision::Nanoseconds));
     }
+
+    #[test]
+    fn test_write_received() {
+        let server = UdpSocket::bind("127.0.0.1:0").unwrap();
+        server.set_read_timeout(Some(Duration::from_secs(5))).unwrap();
+        let addr = server.local_addr().unwrap().to_string();
+
+        let mut client = UdpClient::new(Box::new(LineSerializer::new()));
+        client.add_host(&addr);
+
+        let measurement = Measurement::new("key").with_tag("host", "a").with_field("value", 1i64);
+        assert_eq!(1, client.write_one(measurement, None).unwrap().written);
+
+        let mut buf = [0; 1024];
+        let len = server.recv(&mut buf).unwrap();
+
+        assert_eq!(b"key,host=a value=1i\n", &buf[..len]);
+    }
+
+    #[test]
+    fn test_write_split_into_packets() {
+        let server = UdpSocket::bind("127.0.0.1:0").unwrap();
+        server.set_read_timeout(Some(Duration::from_secs(5))).unwrap();
+        let addr = server.local_addr().unwrap().to_string();
+
+        let mut client = UdpClient::new(Box::new(LineSerializer::new()));
+        client.add_host(&addr);
+        // fits two lines of 7 bytes with newlines
+        client.set_max_packet_len(16);
+
+        let measurements: Vec<Measurement> = vec!["a", "b", "c"].into_iter().map(|key| Measurement::new(key).with_field("v", 1i64)).collect();
+        assert_eq!(3, client.write_many(&measurements, None).unwrap().written);
+
+        let mut buf = [0; 1024];
+
+        let len = server.recv(&mut buf).unwrap();
+        assert_eq!(b"a v=1i\nb v=1i\n", &buf[..len]);
+
+        let len = server.recv(&mut buf).unwrap();
+        assert_eq!(b"c v=1i\n", &buf[..len]);
+    }
+
+    #[test]
+    fn test_measurement_too_large() {
+        let mut client = UdpClient::new(Box::new(LineSerializer::new()));
+        client.add_host("127.0.0.1:8089");
+        client.set_max_packet_len(16);
+
+        let mut measurement = Measurement::new("key");
+        measurement.add_field("value", measurement::Value::String("does not fit into the packet"));
+
+        match client.write_one(measurement, None) {
+            Err(ClientError::MeasurementTooLarge(len)) => assert_eq!(40, len),
+            other => panic!("unexpected result: {:?}", other)
+        }
+
+        let mut small = Measurement::new("a");
+        small.add_field("v", measurement::Value::Integer(1));
+
+        let mut large = Measurement::new("key");
+        large.add_field("value", measurement::Value::String("does not fit into the packet"));
+
+        match client.write_many(&[small, large], None) {
+            Err(ClientError::Interrupted(report, _)) => assert_eq!(Some(1), report.resume_index),
+            other => panic!("unexpected result: {:?}", other)
+        }
+    }
+
+    #[test]
+    fn test_write_serialize_error() {
+        let mut client = UdpClient::new(Box::new(LineSerializer::new()));
+        client.add_host("127.0.0.1:8089");
+
+        let measurements = vec![Measurement::new("a").with_field("v", 1i64), Measurement::new("b").with_field("v", f64::NAN)];
+
+        match client.write_many(&measurements, None) {
+            Err(ClientError::Serialize(1, SerializeError::NonFiniteFloat { .. })) => {},
+            other => panic!("unexpected result: {:?}", other)
+        }
+    }
+
+    struct MockResolver {
+        count: Rc<Cell<u16>>
+    }
+
+    impl Resolver for MockResolver {
+        fn resolve(&self, _: &str) -> io::Result<SocketAddr> {
+            self.count.set(self.count.get() + 1);
+            Ok("127.0.0.1:8089".parse().unwrap())
+        }
+    }
+
+    #[test]
+    fn test_dns_ttl() {
+        let count = Rc::new(Cell::new(0));
+
+        let mut client = UdpClient::new(Box::new(LineSerializer::new()));
+        client.add_host("influxdb:8089");
+        client.set_resolver(Box::new(MockResolver { count: count.clone() }));
+        client.set_dns_ttl(Some(Duration::from_millis(50)));
+
+        client.write_one(Measurement::new("a").with_field("v", 1i64), None).unwrap();
+        client.write_one(Measurement::new("b").with_field("v", 1i64), None).unwrap();
+        assert_eq!(1, count.get());
+
+        thread::sleep(Duration::from_millis(60));
+
+        client.write_one(Measurement::new("c").with_field("v", 1i64), None).unwrap();
+        assert_eq!(2, count.get());
+    }
 }
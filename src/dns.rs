@@ -0,0 +1,136 @@
+use std::net::{ToSocketAddrs, SocketAddr};
+use std::time::{Duration, Instant};
+use std::collections::HashMap;
+use std::cell::RefCell;
+use std::io;
+
+/// Resolves host to the socket address.
+pub trait Resolver {
+    fn resolve(&self, host: &str) -> io::Result<SocketAddr>;
+}
+
+/// Resolver, which uses system's DNS resolution.
+pub struct SystemResolver;
+
+impl Resolver for SystemResolver {
+    fn resolve(&self, host: &str) -> io::Result<SocketAddr> {
+        match try!(host.to_socket_addrs()).last() {
+            Some(addr) => Ok(addr),
+            None => Err(io::Error::new(io::ErrorKind::Other, format!("could not resolve {}", host)))
+        }
+    }
+}
+
+/// Cache of resolved addresses, one per host, e.g. to follow a DNS name of a moving endpoint
+/// without resolving it on every request.
+///
+/// # Examples
+///
+/// ```
+/// use std::time::Duration;
+/// use influent::dns::DnsCache;
+///
+/// let mut cache = DnsCache::new();
+/// cache.set_ttl(Some(Duration::from_secs(30)));
+///
+/// let addr = cache.resolve("127.0.0.1:8086").unwrap();
+/// assert_eq!("127.0.0.1:8086", addr.to_string());
+/// ```
+pub struct DnsCache {
+    resolver: Box<Resolver>,
+    ttl: Option<Duration>,
+    resolved: RefCell<HashMap<String, (SocketAddr, Instant)>>
+}
+
+impl DnsCache {
+    /// Constructs a new `DnsCache` with the `SystemResolver` and without TTL, so nothing is cached.
+    pub fn new() -> DnsCache {
+        DnsCache {
+            resolver: Box::new(SystemResolver),
+            ttl: None,
+            resolved: RefCell::new(HashMap::new())
+        }
+    }
+
+    /// Sets resolver of the hosts, dropping cached addresses.
+    pub fn set_resolver(&mut self, resolver: Box<Resolver>) {
+        self.resolver = resolver;
+        self.resolved.borrow_mut().clear();
+    }
+
+    /// Sets for how long resolved address is reused, dropping cached addresses.
+    pub fn set_ttl(&mut self, ttl: Option<Duration>) {
+        self.ttl = ttl;
+        self.resolved.borrow_mut().clear();
+    }
+
+    /// Returns for how long resolved address is reused.
+    pub fn ttl(&self) -> Option<Duration> {
+        self.ttl
+    }
+
+    /// Resolves `host:port`, reusing the address resolved within the TTL.
+    pub fn resolve(&self, host: &str) -> io::Result<SocketAddr> {
+        let ttl = match self.ttl {
+            Some(ttl) => ttl,
+            None => return self.resolver.resolve(host)
+        };
+
+        match self.resolved.borrow().get(host) {
+            Some(&(addr, at)) if at.elapsed() < ttl => return Ok(addr),
+            _ => {}
+        };
+
+        let addr = try!(self.resolver.resolve(host));
+        self.resolved.borrow_mut().insert(host.to_string(), (addr, Instant::now()));
+
+        Ok(addr)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{DnsCache, Resolver};
+    use std::net::SocketAddr;
+    use std::time::Duration;
+    use std::thread;
+    use std::rc::Rc;
+    use std::cell::RefCell;
+    use std::io;
+
+    struct MockResolver {
+        hosts: Rc<RefCell<Vec<String>>>
+    }
+
+    impl Resolver for MockResolver {
+        fn resolve(&self, host: &str) -> io::Result<SocketAddr> {
+            self.hosts.borrow_mut().push(host.to_string());
+            Ok("127.0.0.1:8086".parse().unwrap())
+        }
+    }
+
+    #[test]
+    fn test_dns_cache() {
+        let hosts = Rc::new(RefCell::new(vec![]));
+
+        let mut cache = DnsCache::new();
+        cache.set_resolver(Box::new(MockResolver { hosts: hosts.clone() }));
+
+        cache.resolve("a:8086").unwrap();
+        cache.resolve("a:8086").unwrap();
+        assert_eq!(2, hosts.borrow().len());
+
+        cache.set_ttl(Some(Duration::from_millis(50)));
+
+        cache.resolve("a:8086").unwrap();
+        cache.resolve("b:8086").unwrap();
+        cache.resolve("a:8086").unwrap();
+        cache.resolve("b:8086").unwrap();
+        assert_eq!(vec!["a:8086", "a:8086", "a:8086", "b:8086"], *hosts.borrow());
+
+        thread::sleep(Duration::from_millis(60));
+
+        cache.resolve("a:8086").unwrap();
+        assert_eq!(5, hosts.borrow().len());
+    }
+}
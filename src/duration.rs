@@ -0,0 +1,151 @@
+use std::time::Duration;
+
+const NANOS_PER_SEC: u64 = 1000000000;
+
+// units from the largest, used by format_duration
+const UNITS: [(&'static str, u64); 7] = [
+    ("d",  86400 * NANOS_PER_SEC),
+    ("h",  3600 * NANOS_PER_SEC),
+    ("m",  60 * NANOS_PER_SEC),
+    ("s",  NANOS_PER_SEC),
+    ("ms", 1000000),
+    ("u",  1000),
+    ("ns", 1)
+];
+
+/// Error of duration literal parsing.
+#[derive(Debug, PartialEq)]
+pub enum DurationError {
+    /// Literal is empty.
+    Empty,
+    /// Number is missing or too large.
+    InvalidNumber(String),
+    /// Unit is missing or unknown.
+    InvalidUnit(String)
+}
+
+fn unit_nanos(unit: &str) -> Option<u64> {
+    match unit {
+        "w"       => Some(7 * 86400 * NANOS_PER_SEC),
+        "u" | "µ" => Some(1000),
+        _ => UNITS.iter().find(|&&(u, _)| u == unit).map(|&(_, n)| n)
+    }
+}
+
+/// Parses duration literal.
+///
+/// # Examples
+///
+/// ```
+/// use std::time::Duration;
+/// use influent::duration::parse_duration;
+///
+/// assert_eq!(Ok(Duration::from_secs(5400)), parse_duration("1h30m"));
+/// assert!(parse_duration("1x").is_err());
+/// ```
+pub fn parse_duration(s: &str) -> Result<Duration, DurationError> {
+    if s.is_empty() {
+        return Err(DurationError::Empty);
+    }
+
+    let mut total: u64 = 0;
+    let mut rest = s;
+
+    while !rest.is_empty() {
+        let digits = rest.find(|c: char| !c.is_digit(10)).unwrap_or(rest.len());
+        let (number, tail) = rest.split_at(digits);
+        let units = tail.find(|c: char| c.is_digit(10)).unwrap_or(tail.len());
+        let (unit, tail) = tail.split_at(units);
+
+        let number = match number.parse::<u64>() {
+            Ok(n) => n,
+            Err(_) => return Err(DurationError::InvalidNumber(number.to_string()))
+        };
+
+        let nanos = match unit_nanos(unit) {
+            Some(n) => n,
+            None => return Err(DurationError::InvalidUnit(unit.to_string()))
+        };
+
+        total = match number.checked_mul(nanos).and_then(|n| n.checked_add(total)) {
+            Some(t) => t,
+            None => return Err(DurationError::InvalidNumber(number.to_string()))
+        };
+
+        rest = tail;
+    }
+
+    Ok(Duration::new(total / NANOS_PER_SEC, (total % NANOS_PER_SEC) as u32))
+}
+
+/// Formats duration as a literal, using units from days to nanoseconds.
+///
+/// # Examples
+///
+/// ```
+/// use std::time::Duration;
+/// use influent::duration::format_duration;
+///
+/// assert_eq!("1h30m", format_duration(Duration::from_secs(5400)));
+/// ```
+pub fn format_duration(d: Duration) -> String {
+    let mut secs = d.as_secs();
+    let mut nanos = d.subsec_nanos() as u64;
+
+    if secs == 0 && nanos == 0 {
+        return "0s".to_string();
+    }
+
+    let mut s = String::new();
+
+    for &(unit, n) in UNITS.iter() {
+        // seconds and their fraction are counted apart, as nanoseconds of huge duration overflow
+        let (rest, n) = if n >= NANOS_PER_SEC { (&mut secs, n / NANOS_PER_SEC) } else { (&mut nanos, n) };
+
+        if *rest >= n {
+            s.push_str(&(*rest / n).to_string());
+            s.push_str(unit);
+            *rest %= n;
+        }
+    }
+
+    s
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{parse_duration, format_duration, DurationError};
+    use std::time::Duration;
+
+    #[test]
+    fn test_parse_duration() {
+        assert_eq!(Ok(Duration::from_secs(5400)), parse_duration("1h30m"));
+        assert_eq!(Ok(Duration::from_secs(7 * 86400)), parse_duration("7d"));
+        assert_eq!(Ok(Duration::from_secs(28 * 86400)), parse_duration("4w"));
+        assert_eq!(Ok(Duration::new(1, 500000000)), parse_duration("1s500ms"));
+        assert_eq!(Ok(Duration::new(0, 10)), parse_duration("10ns"));
+        assert_eq!(Ok(Duration::new(0, 10000)), parse_duration("10u"));
+    }
+
+    #[test]
+    fn test_parse_duration_error() {
+        assert_eq!(Err(DurationError::InvalidUnit("x".to_string())), parse_duration("1x"));
+        assert_eq!(Err(DurationError::InvalidUnit("".to_string())), parse_duration("10"));
+        assert_eq!(Err(DurationError::InvalidNumber("".to_string())), parse_duration("h"));
+        assert_eq!(Err(DurationError::Empty), parse_duration(""));
+    }
+
+    #[test]
+    fn test_format_duration() {
+        assert_eq!("0s", format_duration(Duration::from_secs(0)));
+        assert_eq!("1d2h", format_duration(Duration::from_secs(86400 + 7200)));
+        assert_eq!("1s500ms", format_duration(Duration::new(1, 500000000)));
+
+        assert_eq!("213503982334601d7h15s", format_duration(Duration::from_secs(u64::max_value())));
+        assert_eq!("213503982334601d7h15s999ms999u999ns", format_duration(Duration::new(u64::max_value(), 999999999)));
+
+        for s in &["1h30m", "7d", "10ns"] {
+            assert_eq!(*s, format_duration(parse_duration(s).unwrap()));
+        }
+    }
+}
@@ -0,0 +1,191 @@
+/// Characters escaped in measurement names of the line protocol.
+pub const KEY_SPECIAL: [char; 2] = [',', ' '];
+/// Characters escaped in tag keys, tag values and field keys of the line protocol.
+pub const TAG_SPECIAL: [char; 4] = [',', ' ', '=', '\\'];
+/// Characters escaped in string field values of the line protocol.
+pub const FIELD_VALUE_SPECIAL: [char; 2] = ['\\', '"'];
+const IDENT_SPECIAL: [char; 2] = ['\\', '"'];
+const LITERAL_SPECIAL: [char; 2] = ['\\', '\''];
+
+// Writes `s` to the buffer, prefixing every special character with a backslash.
+fn escape_into(s: &str, special: &[char], buf: &mut String) {
+    for c in s.chars() {
+        if special.contains(&c) {
+            buf.push('\\');
+        }
+        buf.push(c);
+    }
+}
+
+fn escape(s: &str, special: &[char]) -> String {
+    let mut buf = String::with_capacity(s.len());
+    escape_into(s, special, &mut buf);
+    buf
+}
+
+/// Escapes measurement name for the line protocol.
+///
+/// # Examples
+///
+/// ```
+/// use influent::escaping::line_escape_key;
+///
+/// assert_eq!("cpu\\ load\\,total", line_escape_key("cpu load,total"));
+/// ```
+pub fn line_escape_key(s: &str) -> String {
+    escape(s, &KEY_SPECIAL)
+}
+
+/// Same as `line_escape_key`, but writes to the buffer.
+pub fn line_escape_key_into(s: &str, buf: &mut String) {
+    escape_into(s, &KEY_SPECIAL, buf)
+}
+
+/// Escapes tag key, tag value or field key for the line protocol. Backslash is doubled, so
+/// the value ending with it does not escape the following separator.
+///
+/// # Examples
+///
+/// ```
+/// use influent::escaping::line_escape_tag;
+///
+/// assert_eq!("hello\\,\\ gobwas", line_escape_tag("hello, gobwas"));
+/// ```
+pub fn line_escape_tag(s: &str) -> String {
+    escape(s, &TAG_SPECIAL)
+}
+
+/// Same as `line_escape_tag`, but writes to the buffer.
+pub fn line_escape_tag_into(s: &str, buf: &mut String) {
+    escape_into(s, &TAG_SPECIAL, buf)
+}
+
+/// Escapes string field value for the line protocol. Value is not wrapped in quotes.
+///
+/// # Examples
+///
+/// ```
+/// use influent::escaping::line_escape_field_value;
+///
+/// assert_eq!("say \\\"hi\\\"", line_escape_field_value("say \"hi\""));
+/// ```
+pub fn line_escape_field_value(s: &str) -> String {
+    escape(s, &FIELD_VALUE_SPECIAL)
+}
+
+/// Same as `line_escape_field_value`, but writes to the buffer.
+pub fn line_escape_field_value_into(s: &str, buf: &mut String) {
+    escape_into(s, &FIELD_VALUE_SPECIAL, buf)
+}
+
+/// Quotes identifier (database, measurement, tag or field name) for InfluxQL.
+///
+/// # Examples
+///
+/// ```
+/// use influent::escaping::influxql_quote_ident;
+///
+/// let q = format!("select * from {}", influxql_quote_ident("my \"cpu\""));
+///
+/// assert_eq!("select * from \"my \\\"cpu\\\"\"", q);
+/// ```
+pub fn influxql_quote_ident(s: &str) -> String {
+    format!("\"{}\"", escape(s, &IDENT_SPECIAL))
+}
+
+/// Quotes string literal for InfluxQL.
+///
+/// # Examples
+///
+/// ```
+/// use influent::escaping::influxql_quote_literal;
+///
+/// assert_eq!("'Moscow'", influxql_quote_literal("Moscow"));
+/// ```
+pub fn influxql_quote_literal(s: &str) -> String {
+    format!("'{}'", escape(s, &LITERAL_SPECIAL))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{line_escape_key, line_escape_tag, line_escape_field_value, influxql_quote_ident, influxql_quote_literal};
+
+    #[test]
+    fn test_line_escape_key() {
+        assert_eq!("\\ ", line_escape_key(" "));
+        assert_eq!("\\,", line_escape_key(","));
+        assert_eq!("a=b", line_escape_key("a=b"));
+        assert_eq!("cpu\\,\\ total", line_escape_key("cpu, total"));
+    }
+
+    #[test]
+    fn test_line_escape_tag() {
+        assert_eq!("\\ ", line_escape_tag(" "));
+        assert_eq!("\\,", line_escape_tag(","));
+        assert_eq!("hello\\,\\ gobwas", line_escape_tag("hello, gobwas"));
+        assert_eq!("", line_escape_tag(""));
+        assert_eq!("a\\=b", line_escape_tag("a=b"));
+        assert_eq!("C:\\\\path\\\\to", line_escape_tag("C:\\path\\to"));
+        assert_eq!("a\\\\\\,", line_escape_tag("a\\,"));
+    }
+
+    // Reverses escaping of the special characters.
+    fn unescape(s: &str) -> String {
+        let mut buf = String::new();
+        let mut chars = s.chars();
+
+        while let Some(c) = chars.next() {
+            match c {
+                '\\' => buf.extend(chars.next()),
+                c => buf.push(c)
+            }
+        }
+
+        buf
+    }
+
+    #[test]
+    fn test_line_escape_tag_round_trip() {
+        let key = "a=b, c";
+        let value = "x = 1,y=2";
+
+        let line = format!("{}={}", line_escape_tag(key), line_escape_tag(value));
+        assert_eq!("a\\=b\\,\\ c=x\\ \\=\\ 1\\,y\\=2", line);
+
+        // the only unescaped `=` separates key from value
+        let separator = line.char_indices()
+            .find(|&(i, c)| c == '=' && !line[..i].ends_with('\\'))
+            .map(|(i, _)| i)
+            .unwrap();
+
+        assert_eq!(key, unescape(&line[..separator]));
+        assert_eq!(value, unescape(&line[separator + 1..]));
+    }
+
+    #[test]
+    fn test_line_escape_field_value() {
+        assert_eq!("\\\"hello\\\"", line_escape_field_value("\"hello\""));
+        assert_eq!("C:\\\\path", line_escape_field_value("C:\\path"));
+        assert_eq!("\\\\\\\"", line_escape_field_value("\\\""));
+        assert_eq!("a, b=c", line_escape_field_value("a, b=c"));
+    }
+
+    #[test]
+    fn test_influxql_quote_ident() {
+        assert_eq!("\"cpu\"", influxql_quote_ident("cpu"));
+        assert_eq!("\"my db\"", influxql_quote_ident("my db"));
+        assert_eq!("\"a\\\"b\"", influxql_quote_ident("a\"b"));
+        assert_eq!("\"a\\\\b\"", influxql_quote_ident("a\\b"));
+        assert_eq!("\"\"", influxql_quote_ident(""));
+    }
+
+    #[test]
+    fn test_influxql_quote_literal() {
+        assert_eq!("'Moscow'", influxql_quote_literal("Moscow"));
+        assert_eq!("'it\\'s'", influxql_quote_literal("it's"));
+        assert_eq!("'C:\\\\path'", influxql_quote_literal("C:\\path"));
+        assert_eq!("'\\\\\\''", influxql_quote_literal("\\'"));
+        assert_eq!("'say \"hi\"'", influxql_quote_literal("say \"hi\""));
+        assert_eq!("''", influxql_quote_literal(""));
+    }
+}
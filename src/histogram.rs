@@ -0,0 +1,85 @@
+use ::measurement::{Measurement, Value};
+
+/// Pre-aggregated histogram, written as a field per bucket, e.g. `le_0.5=10i`, mirroring the
+/// Prometheus histogram semantics: count of the bucket is the number of observations less
+/// than or equal to its upper bound.
+pub struct Histogram {
+    buckets: Vec<(String, i64)>
+}
+
+impl Histogram {
+    /// Constructs a new `Histogram` from upper bounds of the buckets and their counts.
+    /// Infinite bound is named `le_+Inf`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if number of bounds and counts differ.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use std::f64;
+    /// use influent::histogram::Histogram;
+    /// use influent::measurement::Measurement;
+    /// use influent::serializer::Serializer;
+    /// use influent::serializer::line::LineSerializer;
+    ///
+    /// let histogram = Histogram::new(&[0.1, 0.5, f64::INFINITY], &[3, 8, 10]);
+    ///
+    /// let mut measurement = Measurement::new("latency");
+    /// histogram.add_to(&mut measurement);
+    ///
+    /// assert_eq!("latency le_+Inf=10i,le_0.1=3i,le_0.5=8i", LineSerializer::new().serialize(&measurement));
+    /// ```
+    pub fn new(bounds: &[f64], counts: &[i64]) -> Histogram {
+        assert_eq!(bounds.len(), counts.len(), "number of bounds and counts differ");
+
+        let buckets = bounds.iter().zip(counts.iter())
+            .map(|(bound, count)| {
+                let name = if bound.is_infinite() && *bound > 0.0 { "le_+Inf".to_string() } else { format!("le_{}", bound) };
+                (name, *count)
+            })
+            .collect();
+
+        Histogram {
+            buckets: buckets
+        }
+    }
+
+    /// Adds buckets to the measurement as integer fields.
+    pub fn add_to<'a>(&'a self, measurement: &mut Measurement<'a>) {
+        for &(ref name, count) in self.buckets.iter() {
+            measurement.add_field(name, Value::Integer(count));
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Histogram;
+    use ::measurement::{Measurement, Value};
+    use std::f64;
+
+    #[test]
+    fn test_histogram() {
+        let histogram = Histogram::new(&[0.1, 0.5, 1.0, f64::INFINITY], &[1, 4, 6, 7]);
+
+        let mut measurement = Measurement::new("latency");
+        histogram.add_to(&mut measurement);
+
+        let fields: Vec<String> = measurement.fields.iter()
+            .map(|(name, value)| match value {
+                &Value::Integer(count) => format!("{}={}", name, count),
+                other => panic!("unexpected value: {:?}", other)
+            })
+            .collect();
+
+        assert_eq!(vec!["le_+Inf=7", "le_0.1=1", "le_0.5=4", "le_1=6"], fields);
+    }
+
+    #[test]
+    #[should_panic]
+    fn test_histogram_mismatch() {
+        Histogram::new(&[0.1, 0.5], &[1]);
+    }
+}
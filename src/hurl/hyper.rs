@@ -1,28 +1,83 @@
 extern crate hyper;
 
 use self::hyper::Client as HyperClient;
+use self::hyper::client::Response as HyperResponse;
 use self::hyper::method::Method as HyperMethod;
 use self::hyper::client::Body;
 use self::hyper::Url;
 use self::hyper::header::Connection;
-use self::hyper::header::{Headers, Authorization, Basic};
+use self::hyper::header::{Headers, Authorization, Basic, Host};
 
-use super::{Request, Response, Method, Auth, HurlResult};
-use std::io::Read;
+use super::{Request, Response, Method, Auth, HurlResult, TIMED_OUT};
+use ::dns::{DnsCache, Resolver};
+use std::io::{self, Read, Write};
+use std::time::Duration;
+use std::collections::HashMap;
 
 use super::Hurl;
 
-pub struct HyperHurl;
+/// Hurl over the hyper client.
+///
+/// Requests to hosts with `https` scheme fail without connecting, as hyper 0.6 does not verify
+/// server certificates, and sending credentials over TLS to an unverified server is no safer
+/// than plain `http`.
+///
+/// Timeout, if set, limits reading and writing, but not connecting, which hyper 0.6 does not
+/// support.
+///
+/// DNS TTL, if set, caches resolved addresses of `http` hosts, which are then connected by
+/// the address, sending the host in the `Host` header.
+pub struct HyperHurl {
+    timeout: Option<Duration>,
+    dns: DnsCache
+}
 
 impl HyperHurl {
     pub fn new() -> HyperHurl {
-        HyperHurl
+        HyperHurl {
+            timeout: None,
+            dns: DnsCache::new()
+        }
     }
-}
 
-impl Hurl for HyperHurl {
-    fn request(&self, req: Request) -> HurlResult {
+    /// Sets resolver of the hosts, which is used only when DNS TTL is set.
+    pub fn set_resolver(&mut self, resolver: Box<Resolver>) {
+        self.dns.set_resolver(resolver);
+    }
+
+    // Replaces the host of `http` url with its cached address, keeping the host in the header.
+    fn resolve(&self, url: &mut Url, headers: &mut Headers) -> Result<(), String> {
+        if self.dns.ttl().is_none() || url.scheme != "http" {
+            return Ok(());
+        }
+
+        let (host, port) = match (url.domain(), url.port_or_default()) {
+            (Some(host), Some(port)) => (host.to_string(), port),
+            // IPv6 hosts are addresses already
+            _ => return Ok(())
+        };
+
+        let addr = try!(self.dns.resolve(&format!("{}:{}", host, port)).map_err(|e| format!("could not resolve {}: {:?}", host, e)));
+
+        match url.domain_mut() {
+            Some(domain) => *domain = addr.ip().to_string(),
+            None => {}
+        };
+
+        match url.port_mut() {
+            Some(p) => *p = Some(addr.port()),
+            None => {}
+        };
+
+        headers.set(Host { hostname: host, port: Some(port) });
+
+        Ok(())
+    }
+
+    fn send(&self, req: Request) -> Result<HyperResponse, String> {
         let mut client = HyperClient::new();
+        client.set_read_timeout(self.timeout);
+        client.set_write_timeout(self.timeout);
 
         // map request method to the hyper's
         let method = match req.method {
@@ -39,6 +94,10 @@ impl Hurl for HyperHurl {
             }
         };
 
+        if url.scheme == "https" {
+            return Err(format!("https is not supported, as server certificates are not verified: {}", req.url));
+        }
+
         // if request need to be authorized
         match req.auth {
             Some(auth) => {
@@ -54,6 +113,13 @@ impl Hurl for HyperHurl {
             _ => {}
         };
 
+        // additional headers
+        for (name, value) in req.headers.iter() {
+            headers.set_raw(name.clone(), vec![value.clone().into_bytes()]);
+        }
+
+        try!(self.resolve(&mut url, &mut headers));
+
         // if request has query
         match req.query {
             Some(ref query) => {
@@ -98,19 +164,142 @@ impl Hurl for HyperHurl {
         };
 
         // go!
-        match query.send() {
-            Ok(ref mut resp) => {
-                let mut body = String::new();
-                resp.read_to_string(&mut body).unwrap();
-
-                Ok(Response {
-                    status: resp.status.to_u16(),
-                    body: body
-                })
-            }
-            Err(err) => {
-                Err(format!("something went wrong: {:?}", err))
-            }
+        query.send().map_err(|err| match err {
+            hyper::Error::Io(ref e) if is_timeout(e) => TIMED_OUT.to_string(),
+            err => format!("something went wrong: {:?}", err)
+        })
+    }
+}
+
+fn is_timeout(e: &io::Error) -> bool {
+    e.kind() == io::ErrorKind::TimedOut || e.kind() == io::ErrorKind::WouldBlock
+}
+
+fn read_body(resp: &mut HyperResponse) -> Result<String, String> {
+    let mut body = String::new();
+
+    match resp.read_to_string(&mut body) {
+        Ok(_) => Ok(body),
+        Err(ref e) if is_timeout(e) => Err(TIMED_OUT.to_string()),
+        Err(e) => Err(format!("could not read body: {:?}", e))
+    }
+}
+
+fn to_response(resp: &HyperResponse, body: String) -> Response {
+    let mut headers = HashMap::new();
+    for header in resp.headers.iter() {
+        headers.insert(header.name().to_string(), header.value_string());
+    }
+
+    Response {
+        status: resp.status.to_u16(),
+        headers: headers,
+        body: body
+    }
+}
+
+impl Hurl for HyperHurl {
+    fn request(&self, req: Request) -> HurlResult {
+        let mut resp = try!(self.send(req));
+        let body = try!(read_body(&mut resp));
+
+        Ok(to_response(&resp, body))
+    }
+
+    fn set_timeout(&mut self, timeout: Option<Duration>) {
+        self.timeout = timeout;
+    }
+
+    fn set_dns_ttl(&mut self, ttl: Option<Duration>) {
+        self.dns.set_ttl(ttl);
+    }
+
+    fn request_into(&self, req: Request, w: &mut Write) -> HurlResult {
+        let mut resp = try!(self.send(req));
+
+        // stream only successful response, leaving error reason in the body
+        if resp.status.is_success() {
+            try!(io::copy(&mut resp, w).map_err(|e| if is_timeout(&e) { TIMED_OUT.to_string() } else { format!("could not copy body: {:?}", e) }));
+            return Ok(to_response(&resp, String::new()));
+        }
+
+        let body = try!(read_body(&mut resp));
+
+        Ok(to_response(&resp, body))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::HyperHurl;
+    use super::hyper::Url;
+    use super::hyper::header::{Headers, Host};
+    use ::dns::Resolver;
+    use ::hurl::{Hurl, Request, Method};
+    use std::collections::HashMap;
+    use std::net::SocketAddr;
+    use std::time::Duration;
+    use std::rc::Rc;
+    use std::cell::Cell;
+    use std::io;
+
+    struct MockResolver {
+        count: Rc<Cell<u16>>
+    }
+
+    impl Resolver for MockResolver {
+        fn resolve(&self, _: &str) -> io::Result<SocketAddr> {
+            self.count.set(self.count.get() + 1);
+            Ok("10.0.0.1:8086".parse().unwrap())
         }
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn test_resolve() {
+        let count = Rc::new(Cell::new(0));
+
+        let mut hurl = HyperHurl::new();
+        hurl.set_resolver(Box::new(MockResolver { count: count.clone() }));
+
+        let mut url = Url::parse("http://influxdb:8086/write").unwrap();
+        let mut headers = Headers::new();
+
+        hurl.resolve(&mut url, &mut headers).unwrap();
+        assert_eq!("http://influxdb:8086/write", url.serialize());
+        assert_eq!(0, count.get());
+
+        hurl.set_dns_ttl(Some(Duration::from_secs(60)));
+
+        for _ in 0..2 {
+            let mut url = Url::parse("http://influxdb:8086/write").unwrap();
+            hurl.resolve(&mut url, &mut headers).unwrap();
+            assert_eq!("http://10.0.0.1:8086/write", url.serialize());
+        }
+
+        assert_eq!(1, count.get());
+        assert_eq!(Some(&Host { hostname: "influxdb".to_string(), port: Some(8086) }), headers.get::<Host>());
+
+        let mut url = Url::parse("https://influxdb:8086/write").unwrap();
+        hurl.resolve(&mut url, &mut Headers::new()).unwrap();
+        assert_eq!("https://influxdb:8086/write", url.serialize());
+    }
+
+    #[test]
+    fn test_https_unsupported() {
+        let hurl = HyperHurl::new();
+
+        let result = hurl.request(Request {
+            url: "https://localhost:8086/ping",
+            method: Method::GET,
+            auth: None,
+            query: None,
+            headers: HashMap::new(),
+            body: None
+        });
+
+        match result {
+            Err(e) => assert!(e.starts_with("https is not supported"), e),
+            Ok(r) => panic!("unexpected response: {:?}", r)
+        }
+    }
+}
@@ -0,0 +1,129 @@
+use super::{Request, Response, Method, Hurl, HurlResult};
+use std::collections::HashMap;
+use std::cell::RefCell;
+use std::rc::Rc;
+
+/// Request received by the `MockHurl`.
+#[derive(Debug)]
+pub struct RecordedRequest {
+    pub url: String,
+    pub method: Method,
+    pub query: HashMap<String, String>,
+    pub headers: HashMap<String, String>,
+    pub body: Option<String>
+}
+
+/// Hurl, which records the requests and responds with the canned response, e.g. to test
+/// what is sent for the measurements without running InfluxDB.
+pub struct MockHurl {
+    status: u16,
+    headers: HashMap<String, String>,
+    body: String,
+    requests: Rc<RefCell<Vec<RecordedRequest>>>
+}
+
+impl MockHurl {
+    /// Constructs a new `MockHurl`, responding with `response` to every request.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use std::collections::HashMap;
+    /// use influent::client::{Client, Credentials};
+    /// use influent::client::http::HttpClient;
+    /// use influent::hurl::Response;
+    /// use influent::hurl::mock::MockHurl;
+    /// use influent::measurement::{Measurement, Value};
+    /// use influent::serializer::line::LineSerializer;
+    ///
+    /// let hurl = MockHurl::new(Response { status: 204, headers: HashMap::new(), body: "".to_string() });
+    /// let requests = hurl.requests();
+    ///
+    /// let credentials = Credentials { username: "gobwas", password: "xxx", database: "mydb" };
+    /// let mut client = HttpClient::new(credentials, Box::new(LineSerializer::new()), Box::new(hurl));
+    /// client.add_host("http://localhost:8086");
+    ///
+    /// client.write_one(Measurement::new("key").with_field("value", Value::Integer(1)), None).unwrap();
+    ///
+    /// assert_eq!(Some("key value=1i".to_string()), requests.borrow()[0].body);
+    /// ```
+    pub fn new(response: Response) -> MockHurl {
+        MockHurl {
+            status: response.status,
+            headers: response.headers,
+            body: response.body,
+            requests: Rc::new(RefCell::new(vec![]))
+        }
+    }
+
+    /// Returns handle to the received requests, which stays valid after the hurl is given
+    /// to the client.
+    pub fn requests(&self) -> Rc<RefCell<Vec<RecordedRequest>>> {
+        self.requests.clone()
+    }
+}
+
+impl Hurl for MockHurl {
+    fn request(&self, req: Request) -> HurlResult {
+        let query = match req.query {
+            Some(query) => query.into_iter().map(|(key, value)| (key.to_string(), value)).collect(),
+            None => HashMap::new()
+        };
+
+        self.requests.borrow_mut().push(RecordedRequest {
+            url: req.url.to_string(),
+            method: req.method,
+            query: query,
+            headers: req.headers,
+            body: req.body
+        });
+
+        Ok(Response {
+            status: self.status,
+            headers: self.headers.clone(),
+            body: self.body.clone()
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use ::client::{Client, Credentials};
+    use ::client::http::HttpClient;
+    use ::hurl::{Response, Method};
+    use ::serializer::line::LineSerializer;
+    use ::measurement::{Measurement, Value};
+    use super::MockHurl;
+    use std::collections::HashMap;
+
+    #[test]
+    fn test_mock_hurl() {
+        let hurl = MockHurl::new(Response { status: 204, headers: HashMap::new(), body: "".to_string() });
+        let requests = hurl.requests();
+
+        let credentials = Credentials {
+            username: "gobwas",
+            password: "1234",
+            database: "test"
+        };
+
+        let mut client = HttpClient::new(credentials, Box::new(LineSerializer::new()), Box::new(hurl));
+        client.add_host("http://localhost:8086");
+
+        let measurement = Measurement::new("key").with_tag("tag", "value").with_field("value", Value::Integer(1));
+        assert_eq!(1, client.write_one(measurement, None).unwrap().written);
+
+        let requests = requests.borrow();
+        assert_eq!(1, requests.len());
+
+        let request = &requests[0];
+        assert_eq!("http://localhost:8086/write", request.url);
+        assert_eq!(Some(&"test".to_string()), request.query.get("db"));
+        assert_eq!(Some("key,tag=value value=1i".to_string()), request.body);
+
+        match request.method {
+            Method::POST => {},
+            ref other => panic!("unexpected method: {:?}", other)
+        }
+    }
+}
@@ -1,9 +1,41 @@
 use std::collections::HashMap;
+use std::io::Write;
+use std::time::Duration;
 
 pub mod hyper;
+pub mod mock;
+#[cfg(unix)]
+pub mod unix;
+
+/// Error of the request, which timed out.
+pub const TIMED_OUT: &'static str = "request timed out";
 
 pub trait Hurl {
     fn request(&self, Request) -> HurlResult;
+
+    /// Sets timeout of reading and writing, after which request fails with `TIMED_OUT`.
+    /// By default requests are not limited, as are those of backends without timeouts.
+    fn set_timeout(&mut self, _: Option<Duration>) {}
+
+    /// Sets for how long resolved addresses of the hosts are reused, while still following
+    /// their changes after the `ttl`. By default, as by backends without caching, hosts are
+    /// resolved on every request.
+    fn set_dns_ttl(&mut self, _: Option<Duration>) {}
+
+    /// Makes request, copying body of successful response into the writer. Body of returned
+    /// `Response` is then empty; body of unsuccessful response is returned as usual.
+    ///
+    /// Default implementation buffers the whole body; backends should override it to stream.
+    fn request_into(&self, req: Request, w: &mut Write) -> HurlResult {
+        let mut resp = try!(self.request(req));
+
+        if resp.status / 100 == 2 {
+            try!(w.write_all(resp.body.as_bytes()).map_err(|e| format!("could not write body: {:?}", e)));
+            resp.body.clear();
+        }
+
+        Ok(resp)
+    }
 }
 
 #[derive(Debug)]
@@ -12,15 +44,26 @@ pub struct Request<'a> {
     pub method: Method,
     pub auth: Option<Auth<'a>>,
     pub query: Option<HashMap<&'a str, String>>,
+    pub headers: HashMap<String, String>,
     pub body: Option<String>
 }
 
 #[derive(Debug)]
 pub struct Response {
     pub status: u16,
+    pub headers: HashMap<String, String>,
     pub body: String
 }
 
+impl Response {
+    /// Returns value of the header, matching its name case-insensitively.
+    pub fn header(&self, name: &str) -> Option<&str> {
+        self.headers.iter()
+            .find(|&(key, _)| key.eq_ignore_ascii_case(name))
+            .map(|(_, value)| &value[..])
+    }
+}
+
 impl ToString for Response {
     fn to_string(&self) -> String {
         self.body.clone()
@@ -0,0 +1,294 @@
+extern crate hyper;
+
+use self::hyper::Url;
+use self::hyper::header::{Headers, Authorization, Basic};
+
+use super::{Request, Response, Method, Hurl, HurlResult, TIMED_OUT};
+use std::os::unix::net::UnixStream;
+use std::path::PathBuf;
+use std::io::{self, Read, Write};
+use std::collections::HashMap;
+use std::time::Duration;
+use std::cmp;
+
+/// Hurl, which sends requests over the Unix domain socket, e.g. to the co-located InfluxDB.
+/// Host of the request url is ignored; connection is closed after every request.
+pub struct UnixHurl {
+    path: PathBuf,
+    timeout: Option<Duration>
+}
+
+impl UnixHurl {
+    /// Constructs a new `UnixHurl`, connecting to the socket at `path`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use influent::client::Credentials;
+    /// use influent::client::http::HttpClient;
+    /// use influent::hurl::unix::UnixHurl;
+    /// use influent::serializer::line::LineSerializer;
+    ///
+    /// let credentials = Credentials { username: "gobwas", password: "xxx", database: "mydb" };
+    /// let hurl = UnixHurl::new("/var/run/influxdb.sock");
+    ///
+    /// let mut client = HttpClient::new(credentials, Box::new(LineSerializer::new()), Box::new(hurl));
+    /// client.add_host("http://localhost");
+    /// ```
+    pub fn new<P: Into<PathBuf>>(path: P) -> UnixHurl {
+        UnixHurl {
+            path: path.into(),
+            timeout: None
+        }
+    }
+
+    fn send(&self, req: Request) -> Result<Vec<u8>, String> {
+        let mut url = try!(Url::parse(req.url).map_err(|e| format!("could not parse url: {:?}", e)));
+
+        match req.query {
+            Some(ref query) => url.set_query_from_pairs(query.iter().map(|(key, val)| (*key, &val[..]))),
+            _ => {}
+        };
+
+        let mut target = url.serialize_path().unwrap_or("/".to_string());
+        match url.query {
+            Some(ref query) => {
+                target.push('?');
+                target.push_str(query);
+            }
+            _ => {}
+        };
+
+        let mut headers = Headers::new();
+
+        match req.auth {
+            Some(auth) => {
+                headers.set(Authorization(Basic {
+                    username: auth.username.to_string(),
+                    password: Some(auth.password.to_string())
+                }));
+            }
+            _ => {}
+        };
+
+        for (name, value) in req.headers.iter() {
+            headers.set_raw(name.clone(), vec![value.clone().into_bytes()]);
+        }
+
+        let method = match req.method {
+            Method::POST => "POST",
+            Method::GET  => "GET"
+        };
+
+        let body = req.body.unwrap_or(String::new());
+
+        let head = format!("{} {} HTTP/1.1\r\nHost: localhost\r\nConnection: close\r\nContent-Length: {}\r\n{}\r\n",
+                           method, target, body.len(), headers);
+
+        let mut stream = try!(UnixStream::connect(&self.path).map_err(|e| format!("could not connect to {:?}: {}", self.path, e)));
+
+        try!(stream.set_read_timeout(self.timeout).and_then(|_| stream.set_write_timeout(self.timeout))
+             .map_err(|e| format!("could not set timeout: {}", e)));
+
+        try!(stream.write_all(head.as_bytes()).and_then(|_| stream.write_all(body.as_bytes()))
+             .map_err(|e| io_error("could not send request", e)));
+
+        let mut raw = Vec::new();
+        try!(stream.read_to_end(&mut raw).map_err(|e| io_error("could not read response", e)));
+
+        Ok(raw)
+    }
+}
+
+fn io_error(context: &str, e: io::Error) -> String {
+    match e.kind() {
+        io::ErrorKind::TimedOut | io::ErrorKind::WouldBlock => TIMED_OUT.to_string(),
+        _ => format!("{}: {}", context, e)
+    }
+}
+
+// Decodes body of `Transfer-Encoding: chunked` response.
+fn dechunk(mut raw: &[u8]) -> Result<Vec<u8>, String> {
+    let mut body = Vec::new();
+
+    loop {
+        let eol = try!(raw.windows(2).position(|w| w == b"\r\n").ok_or("malformed chunk".to_string()));
+        let size = String::from_utf8_lossy(&raw[..eol]);
+        let size = try!(usize::from_str_radix(size.split(';').next().unwrap().trim(), 16).map_err(|_| format!("malformed chunk size: {}", size)));
+
+        if size == 0 {
+            return Ok(body);
+        }
+
+        let start = eol + 2;
+        if raw.len() < start + size {
+            return Err("truncated chunk".to_string());
+        }
+
+        body.extend_from_slice(&raw[start..start + size]);
+        raw = &raw[cmp::min(raw.len(), start + size + 2)..];
+    }
+}
+
+fn parse_response(raw: &[u8]) -> HurlResult {
+    let end = try!(raw.windows(4).position(|w| w == b"\r\n\r\n").ok_or("malformed response".to_string()));
+    let head = String::from_utf8_lossy(&raw[..end]);
+    let mut lines = head.split("\r\n");
+
+    // e.g. HTTP/1.1 204 No Content
+    let status_line = lines.next().unwrap_or("");
+    let status = try!(status_line.split(' ').nth(1).and_then(|s| s.parse::<u16>().ok())
+                      .ok_or(format!("malformed status line: {}", status_line)));
+
+    let mut headers = HashMap::new();
+    for line in lines {
+        match line.find(':') {
+            Some(i) => {
+                headers.insert(line[..i].trim().to_string(), line[i + 1..].trim().to_string());
+            }
+            None => {}
+        }
+    }
+
+    let mut response = Response {
+        status: status,
+        headers: headers,
+        body: String::new()
+    };
+
+    let body = match response.header("Transfer-Encoding") {
+        Some(encoding) if encoding.eq_ignore_ascii_case("chunked") => try!(dechunk(&raw[end + 4..])),
+        _ => raw[end + 4..].to_vec()
+    };
+
+    response.body = try!(String::from_utf8(body).map_err(|e| format!("response body is not utf-8: {}", e)));
+
+    Ok(response)
+}
+
+impl Hurl for UnixHurl {
+    fn request(&self, req: Request) -> HurlResult {
+        let raw = try!(self.send(req));
+        parse_response(&raw)
+    }
+
+    fn set_timeout(&mut self, timeout: Option<Duration>) {
+        self.timeout = timeout;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use ::client::{Client, Credentials, ClientError};
+    use ::client::http::HttpClient;
+    use ::serializer::line::LineSerializer;
+    use ::measurement::{Measurement, Value};
+    use super::{UnixHurl, parse_response};
+    use std::os::unix::net::UnixListener;
+    use std::io::{Read, Write};
+    use std::sync::mpsc;
+    use std::thread;
+    use std::env;
+    use std::fs;
+    use std::process;
+    use std::time::{Duration, Instant};
+
+    #[test]
+    fn test_parse_response_chunked() {
+        let raw = b"HTTP/1.1 200 OK\r\nContent-Type: application/json\r\nTransfer-Encoding: chunked\r\n\r\n4\r\n{\"a\"\r\n3\r\n:1}\r\n0\r\n\r\n";
+        let response = parse_response(raw).unwrap();
+
+        assert_eq!(200, response.status);
+        assert_eq!(Some("application/json"), response.header("content-type"));
+        assert_eq!("{\"a\":1}", response.body);
+    }
+
+    #[test]
+    fn test_unix_hurl_timeout() {
+        let path = env::temp_dir().join(format!("influent-timeout-{}.sock", process::id()));
+        let _ = fs::remove_file(&path);
+
+        let listener = UnixListener::bind(&path).unwrap();
+        let (tx, rx) = mpsc::channel::<()>();
+
+        // accepts, but never responds
+        let server = thread::spawn(move || {
+            let (_stream, _) = listener.accept().unwrap();
+            rx.recv().unwrap();
+        });
+
+        let credentials = Credentials {
+            username: "gobwas",
+            password: "1234",
+            database: "test"
+        };
+
+        let mut client = HttpClient::new(credentials, Box::new(LineSerializer::new()), Box::new(UnixHurl::new(path.clone())));
+        client.add_host("http://localhost");
+        client.set_timeout(Some(Duration::from_millis(50)));
+
+        let started = Instant::now();
+
+        match client.write_one(Measurement::new("key").with_field("value", 1i64), None) {
+            Err(ClientError::Timeout) => {},
+            other => panic!("unexpected result: {:?}", other)
+        }
+
+        assert!(started.elapsed() >= Duration::from_millis(50));
+
+        tx.send(()).unwrap();
+        server.join().unwrap();
+        fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn test_unix_hurl() {
+        let path = env::temp_dir().join(format!("influent-{}.sock", process::id()));
+        let _ = fs::remove_file(&path);
+
+        let listener = UnixListener::bind(&path).unwrap();
+        let (tx, rx) = mpsc::channel();
+
+        let server = thread::spawn(move || {
+            let (mut stream, _) = listener.accept().unwrap();
+
+            let mut raw = Vec::new();
+            let mut buf = [0; 1024];
+
+            // client does not shut down writing until the response is read, so read until the known body
+            while !raw.ends_with(b"value=1i") {
+                let n = stream.read(&mut buf).unwrap();
+                if n == 0 {
+                    break;
+                }
+                raw.extend_from_slice(&buf[..n]);
+            }
+
+            tx.send(String::from_utf8(raw).unwrap()).unwrap();
+
+            stream.write_all(b"HTTP/1.1 204 No Content\r\nX-Influxdb-Version: 1.8\r\n\r\n").unwrap();
+        });
+
+        let credentials = Credentials {
+            username: "gobwas",
+            password: "1234",
+            database: "test"
+        };
+
+        let mut client = HttpClient::new(credentials, Box::new(LineSerializer::new()), Box::new(UnixHurl::new(path.clone())));
+        client.add_host("http://localhost");
+
+        let mut measurement = Measurement::new("key");
+        measurement.add_field("value", Value::Integer(1));
+
+        assert_eq!(1, client.write_one(measurement, None).unwrap().written);
+
+        let request = rx.recv().unwrap();
+        server.join().unwrap();
+        fs::remove_file(&path).unwrap();
+
+        assert!(request.starts_with("POST /write?db=test HTTP/1.1\r\n"));
+        assert!(request.contains("Authorization: Basic Z29id2FzOjEyMzQ=\r\n"));
+        assert!(request.ends_with("\r\n\r\nkey value=1i"));
+    }
+}
@@ -1,10 +1,19 @@
 #[macro_use] extern crate log;
+extern crate rustc_serialize;
 
 pub mod client;
 #[cfg(feature = "http")]
 pub mod hurl;
 pub mod serializer;
 pub mod measurement;
+pub mod duration;
+pub mod escaping;
+pub mod dns;
+pub mod testing;
+pub mod histogram;
+pub mod query;
+pub mod parser;
+pub mod monitoring;
 
 use client::{Client, Credentials};
 use client::udp::UdpClient;
@@ -14,11 +23,16 @@ use client::http::HttpClient;
 use hurl::hyper::HyperHurl;
 use serializer::Serializer;
 use serializer::line::LineSerializer;
+#[cfg(feature = "http")]
+use client::rate_limit::RateLimiter;
+#[cfg(feature = "http")]
+use std::time::Duration;
 
 /// Simple factory of `HttpClient` with `LineSerializer`
 ///
 /// Takes two parameters, where first is `Credentials` struct, and second - `Vec<&str>`, where each item
 /// is a InfluxDB host url.
+/// Hosts with `https` scheme are not supported yet, see `HyperHurl` for details.
 ///
 /// # Examples
 ///
@@ -45,6 +59,43 @@ pub fn create_client<'a>(credentials: Credentials<'a>, hosts: Vec<&'a str>) -> H
     client
 }
 
+/// Options of the `HttpClient` made by `create_client_with_options`.
+#[cfg(feature = "http")]
+#[derive(Debug, Clone, Default)]
+pub struct ClientOptions {
+    /// For how long resolved addresses of the hosts are reused, see `HttpClient::set_dns_ttl`.
+    pub dns_ttl: Option<Duration>,
+    /// Limiter of the write requests rate, see `HttpClient::set_rate_limiter`.
+    pub rate_limiter: Option<RateLimiter>
+}
+
+/// Factory of `HttpClient` with `LineSerializer`, like `create_client`, configured with options.
+///
+/// # Examples
+///
+/// ```
+/// use std::time::Duration;
+/// use influent::{create_client_with_options, ClientOptions};
+/// use influent::client::Credentials;
+/// use influent::client::rate_limit::{RateLimiter, RateLimitPolicy};
+///
+/// let credentials = Credentials { username: "gobwas", password: "xxx", database: "mydb" };
+/// let options = ClientOptions {
+///     dns_ttl: Some(Duration::from_secs(30)),
+///     rate_limiter: Some(RateLimiter::new(10.0, 5, RateLimitPolicy::Block))
+/// };
+///
+/// let client = create_client_with_options(credentials, vec!["http://influxdb.default.svc:8086"], options);
+/// ```
+#[cfg(feature = "http")]
+pub fn create_client_with_options<'a>(credentials: Credentials<'a>, hosts: Vec<&'a str>, options: ClientOptions) -> HttpClient<'a> {
+    let mut client = create_client(credentials, hosts);
+    client.set_dns_ttl(options.dns_ttl);
+    client.set_rate_limiter(options.rate_limiter);
+
+    client
+}
+
 /// Simple factory of `UdpClient` with `LineSerializer`
 /// Takes one parameter which is a host and port.
 ///
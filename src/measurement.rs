@@ -1,6 +1,12 @@
+use std::borrow::Cow;
 use std::collections::BTreeMap;
+use std::fmt;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
 
-#[derive(Debug)]
+// number of fields and tags printed by `Debug` of the measurement
+const DEBUG_LIMIT: usize = 10;
+
+#[derive(Debug, Clone, PartialEq)]
 /// Measurement's field value.
 pub enum Value<'a> {
     /// String.
@@ -9,15 +15,137 @@ pub enum Value<'a> {
     Float(f64),
     /// Integer number.
     Integer(i64),
+    /// Unsigned integer number, supported since InfluxDB 1.8.
+    UnsignedInteger(u64),
     /// Boolean value.
     Boolean(bool)
 }
 
-/// Measurement model.
+impl<'a> From<&'a str> for Value<'a> {
+    fn from(s: &'a str) -> Value<'a> {
+        Value::String(s)
+    }
+}
+
+impl<'a> From<f64> for Value<'a> {
+    fn from(f: f64) -> Value<'a> {
+        Value::Float(f)
+    }
+}
+
+impl<'a> From<i64> for Value<'a> {
+    fn from(i: i64) -> Value<'a> {
+        Value::Integer(i)
+    }
+}
+
+impl<'a> From<u64> for Value<'a> {
+    fn from(u: u64) -> Value<'a> {
+        Value::UnsignedInteger(u)
+    }
+}
+
+impl<'a> From<bool> for Value<'a> {
+    fn from(b: bool) -> Value<'a> {
+        Value::Boolean(b)
+    }
+}
+
+/// Serialization of numeric field values.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum NumericPolicy {
+    /// Integers and floats are serialized as they are.
+    Preserve,
+    /// Integers are serialized as floats, and floats always have decimal part.
+    AlwaysFloat
+}
+
+/// Aggregation of the field values on merge of measurements.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Aggregation {
+    /// Values are summed, e.g. for counters.
+    Sum,
+    /// Value of the merged measurement wins, e.g. for gauges.
+    Last,
+    /// Maximum value wins.
+    Max
+}
+
+/// Aggregations of the fields on merge of measurements. Fields without aggregation are
+/// overwritten, as with `Aggregation::Last`.
+pub struct MergePolicy<'a> {
+    fields: BTreeMap<&'a str, Aggregation>
+}
+
+impl<'a> MergePolicy<'a> {
+    /// Constructs a new `MergePolicy`.
+    pub fn new() -> MergePolicy<'a> {
+        MergePolicy {
+            fields: BTreeMap::new()
+        }
+    }
+
+    /// Sets aggregation of the field.
+    pub fn with(mut self, field: &'a str, aggregation: Aggregation) -> MergePolicy<'a> {
+        self.fields.insert(field, aggregation);
+        self
+    }
+
+    fn get(&self, field: &str) -> Aggregation {
+        self.fields.get(field).map(|a| *a).unwrap_or(Aggregation::Last)
+    }
+}
+
+// Numbers of different types are aggregated as floats; other values are not aggregated.
+fn aggregate<'a>(aggregation: Aggregation, current: Value<'a>, value: Value<'a>) -> Value<'a> {
+    match (aggregation, current, value) {
+        (Aggregation::Sum, Value::Integer(a), Value::Integer(b)) => Value::Integer(a.saturating_add(b)),
+        (Aggregation::Sum, Value::Float(a), Value::Float(b))     => Value::Float(a + b),
+        (Aggregation::Sum, Value::Integer(a), Value::Float(b))   => Value::Float(a as f64 + b),
+        (Aggregation::Sum, Value::Float(a), Value::Integer(b))   => Value::Float(a + b as f64),
+        (Aggregation::Max, Value::Integer(a), Value::Integer(b)) => Value::Integer(if a > b { a } else { b }),
+        (Aggregation::Max, Value::Float(a), Value::Float(b))     => Value::Float(a.max(b)),
+        (Aggregation::Max, Value::Integer(a), Value::Float(b))   => Value::Float((a as f64).max(b)),
+        (Aggregation::Max, Value::Float(a), Value::Integer(b))   => Value::Float(a.max(b as f64)),
+        (_, _, value) => value
+    }
+}
+
+/// Tag value for the typed builder, making it distinct from `FieldValue` at the type level.
 #[derive(Debug)]
+pub struct Tag<'a>(pub &'a str);
+
+/// Field value for the typed builder, making it distinct from `Tag` at the type level.
+#[derive(Debug)]
+pub struct FieldValue<'a>(pub Value<'a>);
+
+/// Value of the measurement, which is either `Tag` or `FieldValue`.
+pub trait Attribute<'a> {
+    /// Adds itself to the measurement with the given name.
+    fn add_to(self, name: &'a str, measurement: &mut Measurement<'a>);
+}
+
+impl<'a> Attribute<'a> for Tag<'a> {
+    fn add_to(self, name: &'a str, measurement: &mut Measurement<'a>) {
+        measurement.add_tag(name, self.0);
+    }
+}
+
+impl<'a> Attribute<'a> for FieldValue<'a> {
+    fn add_to(self, name: &'a str, measurement: &mut Measurement<'a>) {
+        measurement.add_field(name, self.0);
+    }
+}
+
+/// Error of strict insertion, when measurement already has the field or tag with the same name.
+#[derive(Debug, PartialEq)]
+pub struct DuplicateKey(pub String);
+
+/// Measurement model.
+#[derive(Clone, PartialEq)]
 pub struct Measurement<'a> {
-    /// Key.
-    pub key: &'a str,
+    /// Key, which is either borrowed or owned, e.g. built from dynamic data.
+    pub key: Cow<'a, str>,
 
     /// Timestamp.
     pub timestamp: Option<i64>,
@@ -26,7 +154,14 @@ pub struct Measurement<'a> {
     pub fields: BTreeMap<&'a str, Value<'a>>,
     
     /// Map of tags.
-    pub tags: BTreeMap<&'a str, &'a str>
+    pub tags: BTreeMap<&'a str, &'a str>,
+
+    /// Numeric policy, which overrides the serializer's one.
+    pub numeric_policy: Option<NumericPolicy>,
+
+    /// Names of the fields in order of insertion, if it is preserved. Fields inserted into
+    /// the map directly are not tracked.
+    pub field_order: Option<Vec<&'a str>>
 }
 
 impl<'a> Measurement<'a> {
@@ -38,17 +173,54 @@ impl<'a> Measurement<'a> {
     /// use influent::measurement::Measurement;
     ///
     /// let measurement = Measurement::new("key");
+    /// let owned = Measurement::new(format!("{}.{}", "cpu", "load"));
     /// ```
-    pub fn new(key: &str) -> Measurement {
+    pub fn new<K: Into<Cow<'a, str>>>(key: K) -> Measurement<'a> {
         Measurement {
-            key: key,
+            key: key.into(),
             timestamp: None,
             fields: BTreeMap::new(),
-            tags: BTreeMap::new()
+            tags: BTreeMap::new(),
+            numeric_policy: None,
+            field_order: None
         }
     }
 
-    /// Adds field to the measurement.
+    /// Constructs a new `Measurement`, which fields are serialized in order of insertion rather
+    /// than alphabetically, e.g. for diffing and debugging. Tags are sorted anyway, as InfluxDB
+    /// recommends.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use influent::serializer::Serializer;
+    /// use influent::serializer::line::LineSerializer;
+    /// use influent::measurement::Measurement;
+    ///
+    /// let measurement = Measurement::ordered("key")
+    ///     .with_field("value", 36.6)
+    ///     .with_field("unit", "C");
+    ///
+    /// assert_eq!("key value=36.6,unit=\"C\"", LineSerializer::new().serialize(&measurement));
+    /// ```
+    pub fn ordered<K: Into<Cow<'a, str>>>(key: K) -> Measurement<'a> {
+        let mut measurement = Measurement::new(key);
+        measurement.field_order = Some(vec![]);
+        measurement
+    }
+
+    fn insert_field(&mut self, field: &'a str, value: Value<'a>) {
+        match self.field_order {
+            Some(ref mut order) if !order.contains(&field) => order.push(field),
+            _ => {}
+        }
+
+        self.fields.insert(field, value);
+    }
+
+    /// Adds field to the measurement. Value could be given as is or as a plain value, which
+    /// converts into it. Existing field with the same name is overwritten; see `try_add_field`
+    /// to detect such collisions.
     ///
     /// # Examples
     ///
@@ -58,9 +230,10 @@ impl<'a> Measurement<'a> {
     /// let mut measurement = Measurement::new("key");
     ///
     /// measurement.add_field("field", Value::String("hello"));
+    /// measurement.add_field("count", 10i64);
     /// ```
-    pub fn add_field(&mut self, field: &'a str, value: Value<'a>) {
-        self.fields.insert(field, value);
+    pub fn add_field<V: Into<Value<'a>>>(&mut self, field: &'a str, value: V) {
+        self.insert_field(field, value.into());
     }
 
     /// Adds tag to the measurement.
@@ -78,7 +251,203 @@ impl<'a> Measurement<'a> {
         self.tags.insert(tag, value);
     }
 
-    /// Sets the timestamp of the measurement. It should be unix timestamp in nanosecond
+    /// Adds fields to the measurement in bulk, overwriting existing ones with the same name.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use influent::measurement::{Measurement, Value};
+    ///
+    /// let mut measurement = Measurement::new("key");
+    ///
+    /// measurement.add_fields(vec![("load", Value::Float(0.5)), ("count", Value::Integer(10))]);
+    ///
+    /// assert_eq!(2, measurement.fields.len());
+    /// ```
+    pub fn add_fields<I: IntoIterator<Item = (&'a str, Value<'a>)>>(&mut self, fields: I) {
+        for (field, value) in fields {
+            self.insert_field(field, value);
+        }
+    }
+
+    /// Adds tags to the measurement in bulk, overwriting existing ones with the same name.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use influent::measurement::Measurement;
+    ///
+    /// let mut measurement = Measurement::new("key");
+    ///
+    /// measurement.add_tags(vec![("host", "a"), ("region", "eu")]);
+    ///
+    /// assert_eq!(Some(&"eu"), measurement.tags.get("region"));
+    /// ```
+    pub fn add_tags<I: IntoIterator<Item = (&'a str, &'a str)>>(&mut self, tags: I) {
+        self.tags.extend(tags);
+    }
+
+    /// Adds field to the measurement, returning it for chaining.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use influent::measurement::{Measurement, Value};
+    ///
+    /// let measurement = Measurement::new("cpu")
+    ///     .with_tag("host", "a")
+    ///     .with_field("load", Value::Float(0.5))
+    ///     .with_timestamp(1434055562000000000);
+    ///
+    /// assert_eq!(Some(&"a"), measurement.tags.get("host"));
+    /// assert_eq!(Some(1434055562000000000), measurement.timestamp);
+    /// ```
+    pub fn with_field<V: Into<Value<'a>>>(mut self, field: &'a str, value: V) -> Measurement<'a> {
+        self.add_field(field, value);
+        self
+    }
+
+    /// Adds tag to the measurement, returning it for chaining.
+    pub fn with_tag(mut self, tag: &'a str, value: &'a str) -> Measurement<'a> {
+        self.add_tag(tag, value);
+        self
+    }
+
+    /// Sets the timestamp of the measurement, returning it for chaining.
+    pub fn with_timestamp(mut self, timestamp: i64) -> Measurement<'a> {
+        self.set_timestamp(timestamp);
+        self
+    }
+
+    /// Adds tag or field to the measurement, depending on the type of the value.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use influent::measurement::{Measurement, Value, Tag, FieldValue};
+    ///
+    /// let measurement = Measurement::new("cpu")
+    ///     .with("host", Tag("server01"))
+    ///     .with("load", FieldValue(Value::Float(0.5)));
+    ///
+    /// assert_eq!(Some(&"server01"), measurement.tags.get("host"));
+    /// assert!(measurement.fields.contains_key("load"));
+    /// ```
+    pub fn with<A: Attribute<'a>>(mut self, name: &'a str, value: A) -> Measurement<'a> {
+        value.add_to(name, &mut self);
+        self
+    }
+
+    /// Adds field to the measurement in strict mode: unlike `add_field`, it returns an error
+    /// instead of overwriting the existing field, surfacing accidental key collisions.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use influent::measurement::{Measurement, Value, DuplicateKey};
+    ///
+    /// let mut measurement = Measurement::new("key");
+    ///
+    /// assert!(measurement.try_add_field("field", Value::Integer(1)).is_ok());
+    /// assert_eq!(Err(DuplicateKey("field".to_string())), measurement.try_add_field("field", Value::Integer(2)));
+    /// ```
+    pub fn try_add_field(&mut self, field: &'a str, value: Value<'a>) -> Result<(), DuplicateKey> {
+        if self.fields.contains_key(field) {
+            return Err(DuplicateKey(field.to_string()));
+        }
+
+        self.insert_field(field, value);
+        Ok(())
+    }
+
+    /// Adds tag to the measurement in strict mode: unlike `add_tag`, it returns an error
+    /// instead of overwriting the existing tag.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use influent::measurement::Measurement;
+    ///
+    /// let mut measurement = Measurement::new("key");
+    ///
+    /// assert!(measurement.try_add_tag("tag", "value").is_ok());
+    /// assert!(measurement.try_add_tag("tag", "other").is_err());
+    /// ```
+    pub fn try_add_tag(&mut self, tag: &'a str, value: &'a str) -> Result<(), DuplicateKey> {
+        if self.tags.contains_key(tag) {
+            return Err(DuplicateKey(tag.to_string()));
+        }
+
+        self.tags.insert(tag, value);
+        Ok(())
+    }
+
+    /// Adds field to the measurement, if it has no field with the same name yet.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use influent::measurement::{Measurement, Value};
+    ///
+    /// let mut measurement = Measurement::new("key");
+    ///
+    /// measurement.add_field("field", Value::Integer(1));
+    /// measurement.add_field_if_absent("field", Value::Integer(2));
+    /// ```
+    pub fn add_field_if_absent(&mut self, field: &'a str, value: Value<'a>) {
+        if !self.fields.contains_key(field) {
+            self.insert_field(field, value);
+        }
+    }
+
+    /// Adds tag to the measurement, if it has no tag with the same name yet.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use influent::measurement::Measurement;
+    ///
+    /// let mut measurement = Measurement::new("key");
+    ///
+    /// measurement.add_tag("region", "Moscow");
+    /// measurement.add_tag_if_absent("region", "default");
+    ///
+    /// assert_eq!(Some(&"Moscow"), measurement.tags.get("region"));
+    /// ```
+    pub fn add_tag_if_absent(&mut self, tag: &'a str, value: &'a str) {
+        self.tags.entry(tag).or_insert(value);
+    }
+
+    /// Removes field from the measurement, returning its value, if it was present.
+    pub fn remove_field(&mut self, field: &str) -> Option<Value<'a>> {
+        match self.field_order {
+            Some(ref mut order) => order.retain(|f| *f != field),
+            None => {}
+        }
+
+        self.fields.remove(field)
+    }
+
+    /// Removes tag from the measurement, returning its value, if it was present, e.g. to strip
+    /// sensitive tags before sending.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use influent::measurement::Measurement;
+    ///
+    /// let mut measurement = Measurement::new("key");
+    /// measurement.add_tag("user", "gobwas");
+    ///
+    /// assert_eq!(Some("gobwas"), measurement.remove_tag("user"));
+    /// assert_eq!(None, measurement.remove_tag("user"));
+    /// ```
+    pub fn remove_tag(&mut self, tag: &str) -> Option<&'a str> {
+        self.tags.remove(tag)
+    }
+
+    /// Sets the timestamp of the measurement. It should be unix timestamp in nanosecond,
+    /// unless other `Precision` is given to the write.
     ///
     /// # Examples
     ///
@@ -92,4 +461,512 @@ impl<'a> Measurement<'a> {
     pub fn set_timestamp(&mut self, timestamp: i64) {
         self.timestamp = Some(timestamp);
     }
-}
\ No newline at end of file
+
+    /// Sets the timestamp of the measurement from the system time, as unix timestamp in
+    /// nanoseconds. Time before the epoch gives negative timestamp; time out of range of
+    /// `i64` nanoseconds (about 292 years around the epoch) is clamped.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use std::time::{Duration, UNIX_EPOCH};
+    /// use influent::measurement::Measurement;
+    ///
+    /// let mut measurement = Measurement::new("key");
+    ///
+    /// measurement.set_timestamp_from(UNIX_EPOCH + Duration::from_secs(1434055562));
+    ///
+    /// assert_eq!(Some(1434055562000000000), measurement.timestamp);
+    /// ```
+    pub fn set_timestamp_from(&mut self, time: SystemTime) {
+        let timestamp = match time.duration_since(UNIX_EPOCH) {
+            Ok(since) => nanoseconds(since),
+            Err(err) => -nanoseconds(err.duration())
+        };
+
+        self.timestamp = Some(timestamp);
+    }
+
+    /// Sets or clears the timestamp of the measurement. It should be unix timestamp in nanosecond
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use influent::measurement::Measurement;
+    ///
+    /// let mut measurement = Measurement::new("key");
+    ///
+    /// measurement.set_timestamp_opt(Some(1434055562000000000));
+    /// measurement.set_timestamp_opt(None);
+    ///
+    /// assert_eq!(None, measurement.timestamp);
+    /// ```
+    pub fn set_timestamp_opt(&mut self, timestamp: Option<i64>) {
+        self.timestamp = timestamp;
+    }
+
+    /// Merges other measurement of the same series into this one, aggregating values of the
+    /// same fields according to the policy. Tags and timestamp of the other measurement win.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use influent::measurement::{Measurement, Value, MergePolicy, Aggregation};
+    ///
+    /// let mut measurement = Measurement::new("requests");
+    /// measurement.add_field("count", Value::Integer(10));
+    ///
+    /// let mut other = Measurement::new("requests");
+    /// other.add_field("count", Value::Integer(5));
+    ///
+    /// measurement.merge(other, &MergePolicy::new().with("count", Aggregation::Sum));
+    ///
+    /// match measurement.fields.get("count") {
+    ///     Some(&Value::Integer(15)) => {},
+    ///     _ => panic!("count is not summed")
+    /// }
+    /// ```
+    pub fn merge(&mut self, other: Measurement<'a>, policy: &MergePolicy) {
+        for (field, value) in other.fields {
+            let merged = match self.fields.remove(field) {
+                Some(current) => aggregate(policy.get(field), current, value),
+                None => value
+            };
+            self.insert_field(field, merged);
+        }
+
+        for (tag, value) in other.tags {
+            self.tags.insert(tag, value);
+        }
+
+        if other.timestamp.is_some() {
+            self.timestamp = other.timestamp;
+        }
+    }
+
+    /// Checks that the measurement could be written: it has fields, and neither its name
+    /// nor names of tags and fields are empty, as InfluxDB rejects such points.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use influent::measurement::{Measurement, ValidationError};
+    ///
+    /// assert_eq!(Err(ValidationError::NoFields), Measurement::new("key").validate());
+    /// assert_eq!(Err(ValidationError::EmptyKey), Measurement::new("").with_field("f", 1i64).validate());
+    /// assert!(Measurement::new("key").with_field("f", 1i64).validate().is_ok());
+    /// ```
+    pub fn validate(&self) -> Result<(), ValidationError> {
+        if self.fields.is_empty() {
+            return Err(ValidationError::NoFields);
+        }
+
+        let has_empty_key = self.key.is_empty()
+            || self.tags.keys().any(|tag| tag.is_empty())
+            || self.fields.keys().any(|field| field.is_empty());
+
+        if has_empty_key {
+            return Err(ValidationError::EmptyKey);
+        }
+
+        Ok(())
+    }
+
+    /// Sets numeric policy of the measurement, which takes precedence over the serializer's one,
+    /// so measurements of different schemas could be written with the same client.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use influent::serializer::Serializer;
+    /// use influent::serializer::line::LineSerializer;
+    /// use influent::measurement::{Measurement, Value, NumericPolicy};
+    ///
+    /// let mut measurement = Measurement::new("key").with_numeric_policy(NumericPolicy::AlwaysFloat);
+    /// measurement.add_field("field", Value::Integer(1));
+    ///
+    /// assert_eq!("key field=1.0", LineSerializer::new().serialize(&measurement));
+    /// ```
+    pub fn with_numeric_policy(mut self, policy: NumericPolicy) -> Measurement<'a> {
+        self.numeric_policy = Some(policy);
+        self
+    }
+}
+
+/// Error of the measurement validation.
+#[derive(Debug, PartialEq)]
+pub enum ValidationError {
+    /// Measurement has no fields, which InfluxDB rejects.
+    NoFields,
+    /// Measurement name, tag or field name is empty.
+    EmptyKey,
+    /// Measurement has more tags than the given limit.
+    TooManyTags(usize)
+}
+
+/// Builder of the measurement, which validates it at `build()`.
+pub struct MeasurementBuilder<'a> {
+    measurement: Measurement<'a>,
+    max_tags: Option<usize>
+}
+
+impl<'a> MeasurementBuilder<'a> {
+    /// Constructs a new `MeasurementBuilder`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use influent::measurement::{MeasurementBuilder, Value, Tag, FieldValue, ValidationError};
+    ///
+    /// let measurement = MeasurementBuilder::new("cpu")
+    ///     .with("host", Tag("server01"))
+    ///     .with("load", FieldValue(Value::Float(0.5)))
+    ///     .build()
+    ///     .unwrap();
+    ///
+    /// assert_eq!(Some(&"server01"), measurement.tags.get("host"));
+    ///
+    /// assert_eq!(Some(ValidationError::NoFields), MeasurementBuilder::new("cpu").build().err());
+    /// ```
+    pub fn new(key: &'a str) -> MeasurementBuilder<'a> {
+        MeasurementBuilder {
+            measurement: Measurement::new(key),
+            max_tags: None
+        }
+    }
+
+    /// Adds tag or field to the measurement, depending on the type of the value.
+    pub fn with<A: Attribute<'a>>(mut self, name: &'a str, value: A) -> MeasurementBuilder<'a> {
+        value.add_to(name, &mut self.measurement);
+        self
+    }
+
+    /// Sets the timestamp of the measurement.
+    pub fn timestamp(mut self, timestamp: i64) -> MeasurementBuilder<'a> {
+        self.measurement.set_timestamp(timestamp);
+        self
+    }
+
+    /// Limits number of tags of the measurement, bounding cardinality of its series.
+    pub fn max_tags(mut self, max: usize) -> MeasurementBuilder<'a> {
+        self.max_tags = Some(max);
+        self
+    }
+
+    /// Validates and returns the measurement.
+    pub fn build(self) -> Result<Measurement<'a>, ValidationError> {
+        try!(self.measurement.validate());
+
+        match self.max_tags {
+            Some(max) if self.measurement.tags.len() > max => return Err(ValidationError::TooManyTags(self.measurement.tags.len())),
+            _ => {}
+        }
+
+        Ok(self.measurement)
+    }
+
+    /// Returns the measurement without validation.
+    pub fn build_unchecked(self) -> Measurement<'a> {
+        self.measurement
+    }
+}
+
+// Writes at most `DEBUG_LIMIT` entries of the map, followed by the number of omitted ones.
+fn fmt_truncated<K: fmt::Debug, V: fmt::Debug>(map: &BTreeMap<K, V>, f: &mut fmt::Formatter) -> fmt::Result {
+    try!(write!(f, "{{"));
+
+    for (i, (key, value)) in map.iter().take(DEBUG_LIMIT).enumerate() {
+        if i > 0 {
+            try!(write!(f, ", "));
+        }
+        try!(write!(f, "{:?}: {:?}", key, value));
+    }
+
+    if map.len() > DEBUG_LIMIT {
+        try!(write!(f, ", ... (+{} more)", map.len() - DEBUG_LIMIT));
+    }
+
+    write!(f, "}}")
+}
+
+fn nanoseconds(duration: Duration) -> i64 {
+    let nanos = (duration.as_secs() as i64).checked_mul(1000000000)
+        .and_then(|n| n.checked_add(duration.subsec_nanos() as i64));
+
+    match nanos {
+        Some(n) if duration.as_secs() <= i64::max_value() as u64 => n,
+        _ => i64::max_value()
+    }
+}
+
+/// Prints at most 10 fields and tags, so that wide measurements do not flood the logs.
+impl<'a> fmt::Debug for Measurement<'a> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        try!(write!(f, "Measurement {{ key: {:?}, timestamp: {:?}, fields: ", self.key, self.timestamp));
+        try!(fmt_truncated(&self.fields, f));
+        try!(write!(f, ", tags: "));
+        try!(fmt_truncated(&self.tags, f));
+        write!(f, " }}")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Measurement, MeasurementBuilder, Value, DuplicateKey, Tag, FieldValue, ValidationError, MergePolicy, Aggregation};
+    use ::serializer::Serializer;
+    use ::serializer::line::LineSerializer;
+    use std::time::{Duration, UNIX_EPOCH};
+
+    #[test]
+    fn test_value_from() {
+        let text = String::from("hello");
+
+        let measurement = Measurement::new("key")
+            .with_field("s", "str")
+            .with_field("owned", &text[..])
+            .with_field("f", 0.5)
+            .with_field("i", -10i64)
+            .with_field("u", 10u64)
+            .with_field("b", true);
+
+        let fields: Vec<String> = measurement.fields.iter().map(|(name, value)| format!("{}={:?}", name, value)).collect();
+
+        assert_eq!(vec!["b=Boolean(true)", "f=Float(0.5)", "i=Integer(-10)", "owned=String(\"hello\")", "s=String(\"str\")", "u=UnsignedInteger(10)"], fields);
+    }
+
+    #[test]
+    fn test_clone() {
+        let template = Measurement::new("key").with_tag("host", "a").with_field("f", 1i64).with_timestamp(10);
+
+        let mut clone = template.clone();
+        assert_eq!(template, clone);
+
+        clone.add_tag("host", "b");
+        clone.add_field("f", 2i64);
+        clone.set_timestamp(20);
+
+        assert!(template != clone);
+        assert_eq!(Some(&"a"), template.tags.get("host"));
+        assert_eq!(Some(&Value::Integer(1)), template.fields.get("f"));
+        assert_eq!(Some(10), template.timestamp);
+    }
+
+    #[test]
+    fn test_add_in_bulk() {
+        let mut measurement = Measurement::ordered("key");
+
+        let fields = vec![("s", Value::String("str")), ("f", Value::Float(0.5)), ("i", Value::Integer(1))];
+        measurement.add_fields(fields.into_iter());
+        measurement.add_tags(vec![("host", "a"), ("region", "eu")]);
+
+        assert_eq!(3, measurement.fields.len());
+        assert_eq!(Some(&Value::String("str")), measurement.fields.get("s"));
+        assert_eq!(Some(&Value::Float(0.5)), measurement.fields.get("f"));
+        assert_eq!(Some(&Value::Integer(1)), measurement.fields.get("i"));
+        assert_eq!(Some(vec!["s", "f", "i"]), measurement.field_order);
+        assert_eq!(Some(&"eu"), measurement.tags.get("region"));
+    }
+
+    #[test]
+    fn test_set_timestamp_from() {
+        let mut measurement = Measurement::new("key");
+
+        measurement.set_timestamp_from(UNIX_EPOCH + Duration::new(1434055562, 123));
+        assert_eq!(Some(1434055562000000123), measurement.timestamp);
+
+        measurement.set_timestamp_from(UNIX_EPOCH - Duration::from_secs(10));
+        assert_eq!(Some(-10000000000), measurement.timestamp);
+
+        measurement.set_timestamp_from(UNIX_EPOCH + Duration::from_secs(u32::max_value() as u64 * 100));
+        assert_eq!(Some(i64::max_value()), measurement.timestamp);
+    }
+
+    #[test]
+    fn test_owned_key() {
+        let names = vec!["cpu", "mem"];
+
+        let measurements: Vec<Measurement> = names.iter()
+            .map(|name| Measurement::new(format!("host.{}", name)).with_field("value", 1i64))
+            .collect();
+
+        assert_eq!("host.cpu", measurements[0].key);
+        assert_eq!("host.mem value=1i", LineSerializer::new().serialize(&measurements[1]));
+        assert_eq!(Measurement::new("host.cpu").with_field("value", 1i64), measurements[0]);
+    }
+
+    #[test]
+    fn test_remove() {
+        let mut measurement = Measurement::new("key")
+            .with_tag("host", "a")
+            .with_tag("user", "gobwas")
+            .with_field("f", 1i64)
+            .with_field("secret", "xxx");
+
+        match measurement.remove_field("secret") {
+            Some(Value::String(s)) => assert_eq!("xxx", s),
+            other => panic!("unexpected value: {:?}", other)
+        }
+        assert!(measurement.remove_field("secret").is_none());
+
+        assert_eq!(Some("gobwas"), measurement.remove_tag("user"));
+        assert_eq!(None, measurement.remove_tag("missing"));
+
+        assert_eq!("key,host=a f=1i", LineSerializer::new().serialize(&measurement));
+    }
+
+    #[test]
+    fn test_validate() {
+        assert_eq!(Err(ValidationError::EmptyKey), Measurement::new("").with_field("f", 1i64).validate());
+        assert_eq!(Err(ValidationError::EmptyKey), Measurement::new("key").with_tag("", "value").with_field("f", 1i64).validate());
+        assert_eq!(Err(ValidationError::NoFields), Measurement::new("key").with_tag("tag", "value").validate());
+        assert_eq!(Ok(()), Measurement::new("key").with_field("f", 1i64).validate());
+    }
+
+    #[test]
+    fn test_add_if_absent() {
+        let mut measurement = Measurement::new("key");
+
+        measurement.add_tag("tag", "explicit");
+        measurement.add_tag_if_absent("tag", "default");
+        measurement.add_tag_if_absent("other", "default");
+
+        measurement.add_field("field", Value::Integer(1));
+        measurement.add_field_if_absent("field", Value::Integer(2));
+
+        assert_eq!(Some(&"explicit"), measurement.tags.get("tag"));
+        assert_eq!(Some(&"default"), measurement.tags.get("other"));
+
+        match measurement.fields.get("field") {
+            Some(&Value::Integer(1)) => {},
+            other => panic!("unexpected field: {:?}", other)
+        }
+
+        measurement.add_field("field", Value::Integer(3));
+        assert_eq!(Some(&Value::Integer(3)), measurement.fields.get("field"));
+        assert_eq!(1, measurement.fields.len());
+    }
+
+    #[test]
+    fn test_try_add_field_duplicate() {
+        let mut measurement = Measurement::new("key");
+
+        assert_eq!(Ok(()), measurement.try_add_field("field", Value::Integer(1)));
+        assert_eq!(Err(DuplicateKey("field".to_string())), measurement.try_add_field("field", Value::Integer(2)));
+
+        match measurement.fields.get("field") {
+            Some(&Value::Integer(1)) => {},
+            other => panic!("unexpected field: {:?}", other)
+        }
+    }
+
+    #[test]
+    fn test_with_typed() {
+        let measurement = Measurement::new("key")
+            .with("host", Tag("server01"))
+            .with("name", FieldValue(Value::String("server01")));
+
+        assert_eq!(1, measurement.tags.len());
+        assert_eq!(Some(&"server01"), measurement.tags.get("host"));
+
+        assert_eq!(1, measurement.fields.len());
+        match measurement.fields.get("name") {
+            Some(&Value::String("server01")) => {},
+            other => panic!("unexpected field: {:?}", other)
+        }
+    }
+
+    #[test]
+    fn test_debug() {
+        let mut measurement = Measurement::new("key");
+        measurement.add_tag("host", "server01");
+        measurement.add_field("value", Value::Integer(1));
+        measurement.set_timestamp(10);
+
+        assert_eq!("Measurement { key: \"key\", timestamp: Some(10), fields: {\"value\": Integer(1)}, tags: {\"host\": \"server01\"} }", format!("{:?}", measurement));
+    }
+
+    #[test]
+    fn test_debug_truncated() {
+        let names: Vec<String> = (0..100).map(|i| format!("f{:03}", i)).collect();
+        let mut measurement = Measurement::new("key");
+
+        for name in names.iter() {
+            measurement.add_field(name, Value::Integer(1));
+        }
+
+        let debug = format!("{:?}", measurement);
+
+        assert!(debug.contains("\"f009\": Integer(1), ... (+90 more)}"));
+        assert!(!debug.contains("f010"));
+    }
+
+    #[test]
+    fn test_builder_no_fields() {
+        let builder = MeasurementBuilder::new("key").with("host", Tag("server01"));
+        assert_eq!(Some(ValidationError::NoFields), builder.build().err());
+
+        let measurement = MeasurementBuilder::new("key").with("host", Tag("server01")).build_unchecked();
+        assert!(measurement.fields.is_empty());
+    }
+
+    #[test]
+    fn test_builder_empty_key() {
+        let builder = MeasurementBuilder::new("").with("value", FieldValue(Value::Integer(1)));
+        assert_eq!(Some(ValidationError::EmptyKey), builder.build().err());
+
+        let builder = MeasurementBuilder::new("key")
+            .with("", Tag("server01"))
+            .with("value", FieldValue(Value::Integer(1)));
+        assert_eq!(Some(ValidationError::EmptyKey), builder.build().err());
+    }
+
+    #[test]
+    fn test_builder_max_tags() {
+        let builder = MeasurementBuilder::new("key")
+            .with("a", Tag("1"))
+            .with("b", Tag("2"))
+            .with("value", FieldValue(Value::Integer(1)))
+            .timestamp(10);
+
+        assert_eq!(Some(ValidationError::TooManyTags(2)), builder.max_tags(1).build().err());
+    }
+
+    #[test]
+    fn test_merge() {
+        let policy = MergePolicy::new()
+            .with("requests", Aggregation::Sum)
+            .with("peak", Aggregation::Max);
+
+        let mut measurement = Measurement::new("http");
+        measurement.add_field("requests", Value::Integer(10));
+        measurement.add_field("peak", Value::Float(3.0));
+        measurement.add_field("load", Value::Float(0.5));
+        measurement.set_timestamp(10);
+
+        let mut other = Measurement::new("http");
+        other.add_field("requests", Value::Integer(5));
+        other.add_field("peak", Value::Integer(2));
+        other.add_field("load", Value::Float(0.7));
+        other.add_field("status", Value::String("ok"));
+        other.set_timestamp(20);
+
+        measurement.merge(other, &policy);
+
+        assert_eq!("{\"load\": Float(0.7), \"peak\": Float(3.0), \"requests\": Integer(15), \"status\": String(\"ok\")}", format!("{:?}", measurement.fields));
+        assert_eq!(Some(20), measurement.timestamp);
+    }
+
+    #[test]
+    fn test_with_chained() {
+        let measurement = Measurement::new("cpu")
+            .with_tag("host", "a")
+            .with_tag("region", "eu")
+            .with_field("load", Value::Float(0.5))
+            .with_field("cores", Value::Integer(4))
+            .with_timestamp(10);
+
+        assert_eq!(vec![(&"host", &"a"), (&"region", &"eu")], measurement.tags.iter().collect::<Vec<_>>());
+        assert_eq!("{\"cores\": Integer(4), \"load\": Float(0.5)}", format!("{:?}", measurement.fields));
+        assert_eq!(Some(10), measurement.timestamp);
+    }
+}
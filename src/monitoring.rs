@@ -0,0 +1,250 @@
+use ::client::{Client, ClientError};
+use ::query::{QueryResult, QueryValue, Series};
+
+/// Statistics of the write service from `_internal` database, counted since start of the server.
+#[derive(Debug, PartialEq)]
+pub struct WriteStats {
+    pub hostname: Option<String>,
+    pub time: String,
+    pub point_req: i64,
+    pub point_req_local: i64,
+    pub req: i64,
+    pub write_ok: i64,
+    pub write_error: i64,
+    pub write_drop: i64,
+    pub write_timeout: i64
+}
+
+/// Statistics of the HTTP service from `_internal` database, counted since start of the server.
+#[derive(Debug, PartialEq)]
+pub struct HttpdStats {
+    pub hostname: Option<String>,
+    pub bind: Option<String>,
+    pub time: String,
+    pub req: i64,
+    pub req_active: i64,
+    pub query_req: i64,
+    pub write_req: i64,
+    pub ping_req: i64,
+    pub points_written_ok: i64,
+    pub points_written_fail: i64,
+    pub client_error: i64,
+    pub server_error: i64,
+    pub auth_fail: i64
+}
+
+/// Statistics of the query executor from `_internal` database, counted since start of the server.
+#[derive(Debug, PartialEq)]
+pub struct QueryExecutorStats {
+    pub hostname: Option<String>,
+    pub time: String,
+    pub queries_active: i64,
+    pub queries_executed: i64,
+    pub queries_finished: i64,
+    pub query_duration_ns: i64
+}
+
+/// Latest statistics, one per tag set (e.g. per host), of the `_internal` measurement.
+pub trait InternalStats: Sized {
+    /// Name of the measurement in `_internal` database.
+    fn measurement() -> &'static str;
+
+    /// Reads statistics from the row.
+    fn from_row(row: &Row) -> Self;
+
+    /// Reads statistics from the rows of the result.
+    fn from_result(result: &QueryResult) -> Result<Vec<Self>, String> {
+        let mut stats = vec![];
+
+        for statement in result.results.iter() {
+            match statement.error {
+                Some(ref error) => return Err(error.clone()),
+                None => {}
+            };
+
+            for series in statement.series.iter() {
+                for values in series.values.iter() {
+                    stats.push(Self::from_row(&Row { series: series, values: values }));
+                }
+            }
+        }
+
+        Ok(stats)
+    }
+}
+
+/// Row of the series, with tags of the series.
+pub struct Row<'a> {
+    series: &'a Series,
+    values: &'a [QueryValue]
+}
+
+impl<'a> Row<'a> {
+    fn value(&self, column: &str) -> Option<&QueryValue> {
+        self.series.columns.iter().position(|c| c == column).and_then(|i| self.values.get(i))
+    }
+
+    /// Returns value of the tag, which is either a tag of the series or a column.
+    pub fn tag(&self, name: &str) -> Option<String> {
+        match self.series.tags.get(name) {
+            Some(value) => Some(value.clone()),
+            None => match self.value(name) {
+                Some(&QueryValue::String(ref value)) => Some(value.clone()),
+                _ => None
+            }
+        }
+    }
+
+    /// Returns time of the row, as it is returned.
+    pub fn time(&self) -> String {
+        match self.value("time") {
+            Some(&QueryValue::String(ref time)) => time.clone(),
+            Some(&QueryValue::Integer(time)) => time.to_string(),
+            _ => String::new()
+        }
+    }
+
+    /// Returns integer counter, which is zero, if it is missing, e.g. in older versions.
+    pub fn counter(&self, column: &str) -> i64 {
+        match self.value(column) {
+            Some(&QueryValue::Integer(i)) => i,
+            Some(&QueryValue::Float(f)) => f as i64,
+            _ => 0
+        }
+    }
+}
+
+impl InternalStats for WriteStats {
+    fn measurement() -> &'static str {
+        "write"
+    }
+
+    fn from_row(row: &Row) -> WriteStats {
+        WriteStats {
+            hostname: row.tag("hostname"),
+            time: row.time(),
+            point_req: row.counter("pointReq"),
+            point_req_local: row.counter("pointReqLocal"),
+            req: row.counter("req"),
+            write_ok: row.counter("writeOk"),
+            write_error: row.counter("writeError"),
+            write_drop: row.counter("writeDrop"),
+            write_timeout: row.counter("writeTimeout")
+        }
+    }
+}
+
+impl InternalStats for HttpdStats {
+    fn measurement() -> &'static str {
+        "httpd"
+    }
+
+    fn from_row(row: &Row) -> HttpdStats {
+        HttpdStats {
+            hostname: row.tag("hostname"),
+            bind: row.tag("bind"),
+            time: row.time(),
+            req: row.counter("req"),
+            req_active: row.counter("reqActive"),
+            query_req: row.counter("queryReq"),
+            write_req: row.counter("writeReq"),
+            ping_req: row.counter("pingReq"),
+            points_written_ok: row.counter("pointsWrittenOK"),
+            points_written_fail: row.counter("pointsWrittenFail"),
+            client_error: row.counter("clientError"),
+            server_error: row.counter("serverError"),
+            auth_fail: row.counter("authFail")
+        }
+    }
+}
+
+impl InternalStats for QueryExecutorStats {
+    fn measurement() -> &'static str {
+        "queryExecutor"
+    }
+
+    fn from_row(row: &Row) -> QueryExecutorStats {
+        QueryExecutorStats {
+            hostname: row.tag("hostname"),
+            time: row.time(),
+            queries_active: row.counter("queriesActive"),
+            queries_executed: row.counter("queriesExecuted"),
+            queries_finished: row.counter("queriesFinished"),
+            query_duration_ns: row.counter("queryDurationNs")
+        }
+    }
+}
+
+/// Queries the latest statistics of the `_internal` measurement, one per tag set.
+///
+/// # Examples
+///
+/// ```
+/// use influent::client::Client;
+/// use influent::monitoring::{internal_stats, WriteStats};
+///
+/// fn log_write_errors(client: &Client) {
+///     match internal_stats::<WriteStats>(client) {
+///         Ok(stats) => for host in stats {
+///             println!("{:?}: {} write errors", host.hostname, host.write_error);
+///         },
+///         Err(err) => println!("could not get stats: {:?}", err)
+///     }
+/// }
+/// ```
+pub fn internal_stats<T: InternalStats>(client: &Client) -> Result<Vec<T>, ClientError> {
+    let q = format!("select * from \"_internal\"..\"{}\" group by * order by time desc limit 1", T::measurement());
+    let result = try!(client.query_typed(q, None));
+
+    T::from_result(&result).map_err(ClientError::Unexpected)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{InternalStats, WriteStats, HttpdStats, QueryExecutorStats};
+    use ::query::QueryResult;
+
+    #[test]
+    fn test_write_stats() {
+        let fixture = "{\"results\":[{\"statement_id\":0,\"series\":[{\"name\":\"write\",\"tags\":{\"hostname\":\"influx-0\"},\"columns\":[\"time\",\"pointReq\",\"pointReqLocal\",\"req\",\"subWriteDrop\",\"subWriteOk\",\"writeDrop\",\"writeError\",\"writeOk\",\"writeTimeout\"],\"values\":[[\"2015-06-11T20:46:02Z\",1500,1500,15,0,15,1,2,12,0]]}]}]}";
+        let result: QueryResult = fixture.parse().unwrap();
+
+        assert_eq!(vec![WriteStats {
+            hostname: Some("influx-0".to_string()),
+            time: "2015-06-11T20:46:02Z".to_string(),
+            point_req: 1500,
+            point_req_local: 1500,
+            req: 15,
+            write_ok: 12,
+            write_error: 2,
+            write_drop: 1,
+            write_timeout: 0
+        }], WriteStats::from_result(&result).unwrap());
+    }
+
+    #[test]
+    fn test_httpd_and_query_executor_stats() {
+        let httpd: QueryResult = "{\"results\":[{\"series\":[{\"name\":\"httpd\",\"tags\":{\"bind\":\":8086\",\"hostname\":\"a\"},\"columns\":[\"time\",\"req\",\"queryReq\",\"writeReq\",\"serverError\"],\"values\":[[\"2015-06-11T20:46:02Z\",10,3,7,1]]},{\"name\":\"httpd\",\"tags\":{\"bind\":\":8086\",\"hostname\":\"b\"},\"columns\":[\"time\",\"req\"],\"values\":[[\"2015-06-11T20:46:02Z\",4]]}]}]}".parse().unwrap();
+        let stats = HttpdStats::from_result(&httpd).unwrap();
+
+        assert_eq!(2, stats.len());
+        assert_eq!((Some("a".to_string()), Some(":8086".to_string())), (stats[0].hostname.clone(), stats[0].bind.clone()));
+        assert_eq!((10, 3, 7, 1), (stats[0].req, stats[0].query_req, stats[0].write_req, stats[0].server_error));
+        assert_eq!((4, 0), (stats[1].req, stats[1].write_req));
+
+        let executor: QueryResult = "{\"results\":[{\"series\":[{\"name\":\"queryExecutor\",\"columns\":[\"time\",\"queriesActive\",\"queriesExecuted\",\"queriesFinished\",\"queryDurationNs\"],\"values\":[[1434055562,1,20,19,123456]]}]}]}".parse().unwrap();
+        let stats = QueryExecutorStats::from_result(&executor).unwrap();
+
+        assert_eq!(vec![QueryExecutorStats {
+            hostname: None,
+            time: "1434055562".to_string(),
+            queries_active: 1,
+            queries_executed: 20,
+            queries_finished: 19,
+            query_duration_ns: 123456
+        }], stats);
+
+        let failed: QueryResult = "{\"results\":[{\"statement_id\":0,\"error\":\"database not found: _internal\"}]}".parse().unwrap();
+        assert!(WriteStats::from_result(&failed).is_err());
+    }
+}
@@ -0,0 +1,386 @@
+use ::measurement::{Measurement, Value};
+use ::escaping::{KEY_SPECIAL, TAG_SPECIAL, FIELD_VALUE_SPECIAL};
+use std::fmt;
+
+const NAME_END: [u8; 2] = [b',', b' '];
+const KEY_END: [u8; 3] = [b'=', b',', b' '];
+const VALUE_END: [u8; 2] = [b',', b' '];
+
+/// Error of the line parsing.
+#[derive(Debug, PartialEq)]
+pub struct ParseError {
+    /// Byte offset in the line, where parsing failed.
+    pub position: usize,
+    pub reason: String
+}
+
+impl fmt::Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{} at {}", self.reason, self.position)
+    }
+}
+
+/// Owned field value of the parsed line.
+#[derive(Debug, PartialEq, Clone)]
+pub enum ParsedValue {
+    String(String),
+    Float(f64),
+    Integer(i64),
+    UnsignedInteger(u64),
+    Boolean(bool)
+}
+
+/// Line protocol line, parsed into unescaped parts. `Measurement` borrowing them is made
+/// with `measurement`.
+#[derive(Debug, PartialEq)]
+pub struct ParsedLine {
+    pub key: String,
+    pub tags: Vec<(String, String)>,
+    pub fields: Vec<(String, ParsedValue)>,
+    pub timestamp: Option<i64>
+}
+
+impl ParsedLine {
+    /// Returns measurement borrowing the parsed parts.
+    pub fn measurement<'a>(&'a self) -> Measurement<'a> {
+        let mut measurement = Measurement::new(&self.key);
+
+        for &(ref tag, ref value) in self.tags.iter() {
+            measurement.add_tag(tag, value);
+        }
+
+        for &(ref field, ref value) in self.fields.iter() {
+            let value = match *value {
+                ParsedValue::String(ref s)          => Value::String(s),
+                ParsedValue::Float(f)               => Value::Float(f),
+                ParsedValue::Integer(i)             => Value::Integer(i),
+                ParsedValue::UnsignedInteger(u)     => Value::UnsignedInteger(u),
+                ParsedValue::Boolean(b)             => Value::Boolean(b)
+            };
+
+            measurement.add_field(field, value);
+        }
+
+        measurement.set_timestamp_opt(self.timestamp);
+
+        measurement
+    }
+}
+
+/// Parses the line of the line protocol, unescaping measurement name, tags and fields.
+///
+/// # Examples
+///
+/// ```
+/// use influent::parser::{parse_line, ParsedValue};
+///
+/// let line = parse_line("cpu\\ load,host=a\\,b value=0.5,state=\"say \\\"hi\\\"\" 1434055562000000000").unwrap();
+///
+/// assert_eq!("cpu load", line.key);
+/// assert_eq!(vec![("host".to_string(), "a,b".to_string())], line.tags);
+/// assert_eq!(ParsedValue::String("say \"hi\"".to_string()), line.fields[1].1);
+/// assert_eq!(Some(1434055562000000000), line.timestamp);
+/// ```
+pub fn parse_line(line: &str) -> Result<ParsedLine, ParseError> {
+    let mut parser = Parser { line: line, pos: 0 };
+
+    let key = parser.token(&NAME_END, &KEY_SPECIAL);
+    if key.is_empty() {
+        return Err(parser.error("measurement name is empty"));
+    }
+
+    let mut tags = vec![];
+    while parser.peek() == Some(b',') {
+        parser.pos += 1;
+
+        let tag = parser.token(&KEY_END, &TAG_SPECIAL);
+        if tag.is_empty() {
+            return Err(parser.error("tag key is empty"));
+        }
+        try!(parser.expect(b'=', "tag without value"));
+
+        let value = parser.token(&VALUE_END, &TAG_SPECIAL);
+        if value.is_empty() {
+            return Err(parser.error("tag value is empty"));
+        }
+
+        tags.push((tag, value));
+    }
+
+    try!(parser.expect(b' ', "measurement without fields"));
+
+    let mut fields = vec![];
+    loop {
+        let field = parser.token(&KEY_END, &TAG_SPECIAL);
+        if field.is_empty() {
+            return Err(parser.error("field key is empty"));
+        }
+        try!(parser.expect(b'=', "field without value"));
+
+        let value = try!(parser.field_value());
+        fields.push((field, value));
+
+        match parser.peek() {
+            Some(b',') => parser.pos += 1,
+            _ => break
+        }
+    }
+
+    let timestamp = match parser.peek() {
+        Some(b' ') => {
+            parser.pos += 1;
+            let rest = parser.line[parser.pos..].trim();
+            Some(try!(rest.parse::<i64>().map_err(|_| parser.error("malformed timestamp"))))
+        },
+        None => None,
+        Some(_) => return Err(parser.error("unexpected character"))
+    };
+
+    Ok(ParsedLine {
+        key: key,
+        tags: tags,
+        fields: fields,
+        timestamp: timestamp
+    })
+}
+
+struct Parser<'a> {
+    line: &'a str,
+    pos: usize
+}
+
+impl<'a> Parser<'a> {
+    fn peek(&self) -> Option<u8> {
+        self.line.as_bytes().get(self.pos).map(|b| *b)
+    }
+
+    fn error(&self, reason: &str) -> ParseError {
+        ParseError {
+            position: self.pos,
+            reason: reason.to_string()
+        }
+    }
+
+    fn expect(&mut self, c: u8, reason: &str) -> Result<(), ParseError> {
+        if self.peek() != Some(c) {
+            return Err(self.error(reason));
+        }
+
+        self.pos += 1;
+        Ok(())
+    }
+
+    // Reads until unescaped terminator, unescaping the special characters of the component,
+    // which are the ones `escaping` escapes in it. Backslash before an ordinary character,
+    // e.g. before `=` or backslash in measurement name, is kept as is.
+    fn token(&mut self, end: &[u8], special: &[char]) -> String {
+        let bytes = self.line.as_bytes();
+        let start = self.pos;
+        let mut buf = Vec::new();
+
+        while self.pos < bytes.len() {
+            let b = bytes[self.pos];
+
+            if b == b'\\' && self.pos + 1 < bytes.len() {
+                let next = bytes[self.pos + 1];
+                if special.contains(&(next as char)) {
+                    buf.push(next);
+                    self.pos += 2;
+                    continue;
+                }
+            }
+
+            if end.contains(&b) {
+                break;
+            }
+
+            buf.push(b);
+            self.pos += 1;
+        }
+
+        // only ascii characters are unescaped, so utf-8 boundaries are kept
+        String::from_utf8(buf).unwrap_or_else(|_| self.line[start..self.pos].to_string())
+    }
+
+    fn field_value(&mut self) -> Result<ParsedValue, ParseError> {
+        if self.peek() == Some(b'"') {
+            return self.string_value();
+        }
+
+        let start = self.pos;
+        let raw = self.token(&VALUE_END, &[]);
+
+        if raw.is_empty() {
+            return Err(self.error("field value is empty"));
+        }
+
+        let value = match &raw[..] {
+            "t" | "T" | "true" | "True" | "TRUE" => ParsedValue::Boolean(true),
+            "f" | "F" | "false" | "False" | "FALSE" => ParsedValue::Boolean(false),
+            _ if raw.ends_with('i') => match raw[..raw.len() - 1].parse::<i64>() {
+                Ok(i) => ParsedValue::Integer(i),
+                Err(_) => return Err(ParseError { position: start, reason: format!("malformed integer: {}", raw) })
+            },
+            _ if raw.ends_with('u') => match raw[..raw.len() - 1].parse::<u64>() {
+                Ok(u) => ParsedValue::UnsignedInteger(u),
+                Err(_) => return Err(ParseError { position: start, reason: format!("malformed unsigned integer: {}", raw) })
+            },
+            // NaN and infinities are invalid in line protocol, as the serializer never writes them
+            _ => match raw.parse::<f64>() {
+                Ok(f) if !f.is_finite() => return Err(ParseError { position: start, reason: format!("non-finite float: {}", raw) }),
+                Ok(f) => ParsedValue::Float(f),
+                Err(_) => return Err(ParseError { position: start, reason: format!("malformed value: {}", raw) })
+            }
+        };
+
+        Ok(value)
+    }
+
+    fn string_value(&mut self) -> Result<ParsedValue, ParseError> {
+        let bytes = self.line.as_bytes();
+        let mut buf = Vec::new();
+
+        // opening quote
+        self.pos += 1;
+
+        while self.pos < bytes.len() {
+            match bytes[self.pos] {
+                b'\\' if self.pos + 1 < bytes.len() && FIELD_VALUE_SPECIAL.contains(&(bytes[self.pos + 1] as char)) => {
+                    buf.push(bytes[self.pos + 1]);
+                    self.pos += 2;
+                },
+                b'"' => {
+                    self.pos += 1;
+                    return String::from_utf8(buf)
+                        .map(ParsedValue::String)
+                        .map_err(|_| self.error("string value is not utf-8"));
+                },
+                b => {
+                    buf.push(b);
+                    self.pos += 1;
+                }
+            }
+        }
+
+        Err(self.error("unterminated string value"))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{parse_line, ParsedValue};
+    use ::measurement::Measurement;
+    use ::escaping::{line_escape_key, line_escape_tag, line_escape_field_value};
+    use ::serializer::Serializer;
+    use ::serializer::line::LineSerializer;
+
+    #[test]
+    fn test_parse_line() {
+        let line = parse_line("key,one\\ \\,two=three\\,\\ four b=f,i=-10i,u=18446744073709551615u,s=\"a \\\"b\\\" \\\\ c\" 10").unwrap();
+
+        assert_eq!("key", line.key);
+        assert_eq!(vec![("one ,two".to_string(), "three, four".to_string())], line.tags);
+        assert_eq!(vec![
+            ("b".to_string(), ParsedValue::Boolean(false)),
+            ("i".to_string(), ParsedValue::Integer(-10)),
+            ("u".to_string(), ParsedValue::UnsignedInteger(u64::max_value())),
+            ("s".to_string(), ParsedValue::String("a \"b\" \\ c".to_string()))
+        ], line.fields);
+        assert_eq!(Some(10), line.timestamp);
+    }
+
+    #[test]
+    fn test_parse_line_round_trip() {
+        let serializer = LineSerializer::new();
+
+        let lines = vec![
+            "key,one\\ \\,two=three\\,\\ four,tag=value b=f,f=10,i=10i,one\\,\\ two=\"three\",s=\"string\" 10",
+            "key s=\"string\" 1434055562000000000",
+            "key s=\"string\"",
+            "key a=1i,c=0.5",
+            "key,tag=value field=\"value\"",
+            "key u=18446744073709551615u",
+            "cpu\\ load,host=a\\=b s=\"say \\\"hi\\\"\"",
+            "key,path=C:\\\\path\\\\to f=1i",
+            "C:\\path\\to,a\\\\=b\\\\ f\\\\=\"\\\\\"",
+            "a=b\\,\\ c,tag=a\\=b a\\=b=-0.5",
+            "key,tag=value\\\\ field\\\\=1i"
+        ];
+
+        for line in lines {
+            let parsed = parse_line(line).unwrap();
+            assert_eq!(line, serializer.serialize(&parsed.measurement()));
+        }
+    }
+
+    #[test]
+    fn test_parse_line_escaping_per_component() {
+        // backslash is escaped in tags and field keys, but not in measurement names
+        let line = parse_line("C:\\path\\to\\,x,C:\\\\path=a\\\\b C:\\\\path=\"C:\\\\path\"").unwrap();
+
+        assert_eq!("C:\\path\\to,x", line.key);
+        assert_eq!(vec![("C:\\path".to_string(), "a\\b".to_string())], line.tags);
+        assert_eq!(vec![("C:\\path".to_string(), ParsedValue::String("C:\\path".to_string()))], line.fields);
+
+        // `=` is not escaped in measurement names, so its backslash is kept
+        assert_eq!("a\\=b", parse_line("a\\=b f=1i").unwrap().key);
+    }
+
+    #[test]
+    fn test_parse_line_unescapes_as_escaped() {
+        for b in 32u8..127 {
+            let s = format!("a{}b", b as char);
+
+            let line = format!("{},{}={} {}=\"{}\"", line_escape_key(&s), line_escape_tag(&s), line_escape_tag(&s),
+                               line_escape_tag(&s), line_escape_field_value(&s));
+            let parsed = parse_line(&line).unwrap();
+
+            assert_eq!(s, parsed.key, "line: {}", line);
+            assert_eq!(vec![(s.clone(), s.clone())], parsed.tags, "line: {}", line);
+            assert_eq!(vec![(s.clone(), ParsedValue::String(s.clone()))], parsed.fields, "line: {}", line);
+        }
+    }
+
+    #[test]
+    fn test_parse_line_measurement_round_trip() {
+        let serializer = LineSerializer::new();
+
+        let measurements = vec![
+            Measurement::new("C:\\path, a=b").with_tag("C:\\path, a=b", "C:\\path, a=b").with_field("C:\\path, a=b", "C:\\path, \"a\"=b"),
+            Measurement::new("key").with_tag("tag", "value\\").with_field("f\\", 0.1f64).with_timestamp(-10),
+            Measurement::new("key").with_field("i", i64::min_value()).with_field("u", u64::max_value()).with_field("b", true)
+        ];
+
+        for measurement in measurements {
+            let line = serializer.serialize(&measurement);
+            let parsed = parse_line(&line).unwrap();
+
+            assert_eq!(measurement, parsed.measurement(), "line: {}", line);
+        }
+    }
+
+    #[test]
+    fn test_parse_line_malformed() {
+        let cases = vec![
+            ("", 0, "measurement name is empty"),
+            ("key", 3, "measurement without fields"),
+            ("key,tag value=1", 7, "tag without value"),
+            ("key,tag= value=1", 8, "tag value is empty"),
+            ("key value", 9, "field without value"),
+            ("key value=", 10, "field value is empty"),
+            ("key value=\"open", 15, "unterminated string value"),
+            ("key value=1x", 10, "malformed value: 1x"),
+            ("key value=1.5i", 10, "malformed integer: 1.5i"),
+            ("key value=1 yesterday", 12, "malformed timestamp"),
+            ("key value=NaN", 10, "non-finite float: NaN"),
+            ("key value=inf", 10, "non-finite float: inf"),
+            ("key value=-infinity", 10, "non-finite float: -infinity"),
+            ("key value=1e400", 10, "non-finite float: 1e400")
+        ];
+
+        for (line, position, reason) in cases {
+            let err = parse_line(line).unwrap_err();
+            assert_eq!((position, reason), (err.position, &err.reason[..]), "line: {}", line);
+        }
+    }
+}
@@ -0,0 +1,389 @@
+use rustc_serialize::Decodable;
+use rustc_serialize::json::{Json, Object, Decoder};
+use std::collections::HashMap;
+use std::str::FromStr;
+
+/// Parsed response of the query, e.g. `{"results":[{"series":[...]}]}`.
+#[derive(Debug, PartialEq)]
+pub struct QueryResult {
+    /// Results of the statements, in order of the statements in the query.
+    pub results: Vec<StatementResult>
+}
+
+/// Result of the single statement of the query.
+#[derive(Debug, PartialEq)]
+pub struct StatementResult {
+    pub statement_id: Option<u64>,
+    pub series: Vec<Series>,
+    /// Error of the statement, e.g. if the measurement is not found.
+    pub error: Option<String>
+}
+
+/// Rows of the single series.
+#[derive(Debug, PartialEq)]
+pub struct Series {
+    pub name: String,
+    pub tags: HashMap<String, String>,
+    pub columns: Vec<String>,
+    /// Rows, each having a value per column.
+    pub values: Vec<Vec<QueryValue>>
+}
+
+/// Value of the row cell.
+#[derive(Debug, PartialEq, Clone)]
+pub enum QueryValue {
+    Null,
+    Boolean(bool),
+    Integer(i64),
+    Float(f64),
+    String(String)
+}
+
+/// Parser of the `/query` response, e.g. to swap in a faster JSON parser for large responses.
+pub trait ResultParser {
+    fn parse(&self, body: &str) -> Result<QueryResult, String>;
+}
+
+/// Default parser of the response, based on rustc-serialize.
+pub struct JsonParser;
+
+impl ResultParser for JsonParser {
+    fn parse(&self, body: &str) -> Result<QueryResult, String> {
+        body.parse()
+    }
+}
+
+impl QueryResult {
+    /// Decodes rows of all the series into values, e.g. structs with fields named as
+    /// the columns. Fails on the first statement error.
+    pub fn rows<T: Decodable>(&self) -> Result<Vec<T>, String> {
+        let mut rows = vec![];
+
+        for result in self.results.iter() {
+            match result.error {
+                Some(ref error) => return Err(error.clone()),
+                None => {}
+            };
+
+            for series in result.series.iter() {
+                rows.extend(try!(series.rows()));
+            }
+        }
+
+        Ok(rows)
+    }
+
+    /// Decodes the single value of the first row of the first series, e.g. of `select count(*)`,
+    /// ignoring `time` column. Returns `None` if there are no series or the value is null,
+    /// and fails if the row has more than one value.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use influent::query::QueryResult;
+    ///
+    /// let result: QueryResult = "{\"results\":[{\"series\":[{\"name\":\"cpu\",\"columns\":[\"time\",\"count\"],\"values\":[[\"1970-01-01T00:00:00Z\",42]]}]}]}".parse().unwrap();
+    ///
+    /// assert_eq!(Some(42), result.scalar::<u64>().unwrap());
+    /// ```
+    pub fn scalar<T: Decodable>(&self) -> Result<Option<T>, String> {
+        let result = match self.results.first() {
+            Some(result) => result,
+            None => return Ok(None)
+        };
+
+        match result.error {
+            Some(ref error) => return Err(error.clone()),
+            None => {}
+        };
+
+        let series = match result.series.first() {
+            Some(series) => series,
+            None => return Ok(None)
+        };
+
+        let row = match series.values.first() {
+            Some(row) => row,
+            None => return Ok(None)
+        };
+
+        let cells: Vec<&QueryValue> = series.columns.iter().zip(row.iter())
+            .filter(|&(column, _)| column != "time")
+            .map(|(_, value)| value)
+            .collect();
+
+        match cells.len() {
+            1 => {},
+            n => return Err(format!("result is not scalar: row has {} values", n))
+        };
+
+        match *cells[0] {
+            QueryValue::Null => Ok(None),
+            ref value => {
+                let mut decoder = Decoder::new(json_of(value));
+                T::decode(&mut decoder).map(Some).map_err(|e| format!("could not decode value: {}", e))
+            }
+        }
+    }
+}
+
+impl Series {
+    /// Decodes rows into values, e.g. structs with fields named as the columns. Column `time`
+    /// is a RFC3339 string, or an integer if the query is made with epoch.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use std::collections::BTreeMap;
+    /// use influent::query::QueryResult;
+    ///
+    /// let result: QueryResult = "{\"results\":[{\"series\":[{\"name\":\"cpu\",\"columns\":[\"time\",\"value\"],\"values\":[[1434055562,0.5]]}]}]}".parse().unwrap();
+    ///
+    /// let rows: Vec<BTreeMap<String, f64>> = result.results[0].series[0].rows().unwrap();
+    ///
+    /// assert_eq!(Some(&0.5), rows[0].get("value"));
+    /// ```
+    pub fn rows<T: Decodable>(&self) -> Result<Vec<T>, String> {
+        let mut rows = vec![];
+
+        for values in self.values.iter() {
+            let object: Object = self.columns.iter().cloned()
+                .zip(values.iter().map(json_of))
+                .collect();
+
+            let mut decoder = Decoder::new(Json::Object(object));
+            rows.push(try!(T::decode(&mut decoder).map_err(|e| format!("could not decode row: {}", e))));
+        }
+
+        Ok(rows)
+    }
+}
+
+impl FromStr for QueryResult {
+    type Err = String;
+
+    /// Parses the JSON response of the `/query` endpoint.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use influent::query::{QueryResult, QueryValue};
+    ///
+    /// let result: QueryResult = "{\"results\":[{\"series\":[{\"name\":\"cpu\",\"columns\":[\"time\",\"value\"],\"values\":[[\"2015-06-11T20:46:02Z\",0.5]]}]}]}".parse().unwrap();
+    ///
+    /// assert_eq!(QueryValue::Float(0.5), result.results[0].series[0].values[0][1]);
+    /// ```
+    fn from_str(s: &str) -> Result<QueryResult, String> {
+        let json = try!(Json::from_str(s).map_err(|e| format!("could not parse query result: {}", e)));
+        let root = try!(json.as_object().ok_or("query result is not an object".to_string()));
+
+        let results = match root.get("results") {
+            Some(&Json::Array(ref results)) => try!(results.iter().map(statement_result).collect()),
+            Some(_) => return Err("results is not an array".to_string()),
+            None => match root.get("error") {
+                Some(&Json::String(ref error)) => return Err(error.clone()),
+                _ => vec![]
+            }
+        };
+
+        Ok(QueryResult {
+            results: results
+        })
+    }
+}
+
+fn statement_result(json: &Json) -> Result<StatementResult, String> {
+    let object = try!(json.as_object().ok_or("statement result is not an object".to_string()));
+
+    let series = match object.get("series") {
+        Some(&Json::Array(ref series)) => try!(series.iter().map(series_of).collect()),
+        Some(_) => return Err("series is not an array".to_string()),
+        None => vec![]
+    };
+
+    Ok(StatementResult {
+        statement_id: object.get("statement_id").and_then(|id| id.as_u64()),
+        series: series,
+        error: object.get("error").and_then(|e| e.as_string()).map(|e| e.to_string())
+    })
+}
+
+fn series_of(json: &Json) -> Result<Series, String> {
+    let object = try!(json.as_object().ok_or("series is not an object".to_string()));
+
+    let columns = try!(strings(object, "columns"));
+
+    let tags = match object.get("tags") {
+        Some(&Json::Object(ref tags)) => tags.iter()
+            .map(|(key, value)| (key.clone(), value.as_string().unwrap_or("").to_string()))
+            .collect(),
+        _ => HashMap::new()
+    };
+
+    let values = match object.get("values") {
+        Some(&Json::Array(ref rows)) => {
+            let mut values = vec![];
+
+            for row in rows {
+                match *row {
+                    Json::Array(ref row) => values.push(row.iter().map(value_of).collect()),
+                    _ => return Err("row is not an array".to_string())
+                }
+            }
+
+            values
+        },
+        Some(_) => return Err("values is not an array".to_string()),
+        None => vec![]
+    };
+
+    Ok(Series {
+        name: object.get("name").and_then(|n| n.as_string()).unwrap_or("").to_string(),
+        tags: tags,
+        columns: columns,
+        values: values
+    })
+}
+
+fn strings(object: &Object, key: &str) -> Result<Vec<String>, String> {
+    match object.get(key) {
+        Some(&Json::Array(ref items)) => items.iter()
+            .map(|item| item.as_string().map(|s| s.to_string()).ok_or(format!("{} contains not a string", key)))
+            .collect(),
+        Some(_) => Err(format!("{} is not an array", key)),
+        None => Ok(vec![])
+    }
+}
+
+fn value_of(json: &Json) -> QueryValue {
+    match *json {
+        Json::Null => QueryValue::Null,
+        Json::Boolean(b) => QueryValue::Boolean(b),
+        Json::I64(i) => QueryValue::Integer(i),
+        Json::U64(u) if u <= i64::max_value() as u64 => QueryValue::Integer(u as i64),
+        Json::U64(u) => QueryValue::Float(u as f64),
+        Json::F64(f) => QueryValue::Float(f),
+        Json::String(ref s) => QueryValue::String(s.clone()),
+        // nested values are not produced by InfluxDB, so keep them as is
+        ref other => QueryValue::String(other.to_string())
+    }
+}
+
+fn json_of(value: &QueryValue) -> Json {
+    match *value {
+        QueryValue::Null => Json::Null,
+        QueryValue::Boolean(b) => Json::Boolean(b),
+        QueryValue::Integer(i) => Json::I64(i),
+        QueryValue::Float(f) => Json::F64(f),
+        QueryValue::String(ref s) => Json::String(s.clone())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{QueryResult, QueryValue, ResultParser, JsonParser};
+    use rustc_serialize::{Decodable, Decoder};
+
+    #[test]
+    fn test_query_result() {
+        let fixture = "{\"results\":[{\"series\":[{\"name\":\"sut\",\"columns\":[\"time\",\"boolean\",\"float\",\"integer\",\"string\",\"tag\",\"tag, with comma\",\"with, comma\"],\"values\":[[\"2015-06-11T20:46:02Z\",false,10,10,\"string\",\"value\",\"three, four\",\"comma, with\"]]}]}]}";
+        let result: QueryResult = fixture.parse().unwrap();
+
+        assert_eq!(1, result.results.len());
+        assert_eq!(1, result.results[0].series.len());
+
+        let series = &result.results[0].series[0];
+        assert_eq!("sut", series.name);
+        assert_eq!(vec!["time", "boolean", "float", "integer", "string", "tag", "tag, with comma", "with, comma"], series.columns);
+        assert_eq!(vec![vec![
+            QueryValue::String("2015-06-11T20:46:02Z".to_string()),
+            QueryValue::Boolean(false),
+            QueryValue::Integer(10),
+            QueryValue::Integer(10),
+            QueryValue::String("string".to_string()),
+            QueryValue::String("value".to_string()),
+            QueryValue::String("three, four".to_string()),
+            QueryValue::String("comma, with".to_string())
+        ]], series.values);
+    }
+
+    struct Row {
+        time: String,
+        integer: i64,
+        boolean: bool
+    }
+
+    impl Decodable for Row {
+        fn decode<D: Decoder>(d: &mut D) -> Result<Row, D::Error> {
+            d.read_struct("Row", 3, |d| {
+                Ok(Row {
+                    time: try!(d.read_struct_field("time", 0, Decodable::decode)),
+                    integer: try!(d.read_struct_field("integer", 1, Decodable::decode)),
+                    boolean: try!(d.read_struct_field("boolean", 2, Decodable::decode))
+                })
+            })
+        }
+    }
+
+    #[test]
+    fn test_query_result_rows() {
+        let fixture = "{\"results\":[{\"series\":[{\"name\":\"sut\",\"columns\":[\"time\",\"boolean\",\"float\",\"integer\",\"string\"],\"values\":[[\"2015-06-11T20:46:02Z\",false,10,10,\"string\"],[\"2015-06-11T20:46:03Z\",true,0.5,-1,\"other\"]]}]}]}";
+        let result: QueryResult = fixture.parse().unwrap();
+
+        let rows: Vec<Row> = result.rows().unwrap();
+
+        assert_eq!(2, rows.len());
+        assert_eq!(("2015-06-11T20:46:02Z", 10, false), (&rows[0].time[..], rows[0].integer, rows[0].boolean));
+        assert_eq!(("2015-06-11T20:46:03Z", -1, true), (&rows[1].time[..], rows[1].integer, rows[1].boolean));
+
+        let missing: QueryResult = "{\"results\":[{\"series\":[{\"name\":\"sut\",\"columns\":[\"time\"],\"values\":[[\"2015-06-11T20:46:02Z\"]]}]}]}".parse().unwrap();
+        assert!(missing.rows::<Row>().is_err());
+
+        let failed: QueryResult = "{\"results\":[{\"statement_id\":0,\"error\":\"database not found: test\"}]}".parse().unwrap();
+        assert_eq!(Err("database not found: test".to_string()), failed.rows::<Row>().map(|rows| rows.len()));
+    }
+
+    #[test]
+    fn test_query_result_scalar() {
+        let result: QueryResult = "{\"results\":[{\"series\":[{\"name\":\"cpu\",\"columns\":[\"time\",\"last\"],\"values\":[[\"2015-06-11T20:46:02Z\",0.5],[\"2015-06-11T20:46:03Z\",1.5]]}]}]}".parse().unwrap();
+        assert_eq!(Some(0.5), result.scalar::<f64>().unwrap());
+
+        let empty: QueryResult = "{\"results\":[{\"statement_id\":0}]}".parse().unwrap();
+        assert_eq!(None, empty.scalar::<u64>().unwrap());
+
+        let null: QueryResult = "{\"results\":[{\"series\":[{\"name\":\"cpu\",\"columns\":[\"time\",\"last\"],\"values\":[[\"2015-06-11T20:46:02Z\",null]]}]}]}".parse().unwrap();
+        assert_eq!(None, null.scalar::<f64>().unwrap());
+
+        let wide: QueryResult = "{\"results\":[{\"series\":[{\"name\":\"cpu\",\"columns\":[\"time\",\"a\",\"b\"],\"values\":[[\"2015-06-11T20:46:02Z\",1,2]]}]}]}".parse().unwrap();
+        assert_eq!(Err("result is not scalar: row has 2 values".to_string()), wide.scalar::<u64>());
+
+        let mistyped: QueryResult = "{\"results\":[{\"series\":[{\"name\":\"cpu\",\"columns\":[\"time\",\"s\"],\"values\":[[\"2015-06-11T20:46:02Z\",\"text\"]]}]}]}".parse().unwrap();
+        assert!(mistyped.scalar::<u64>().is_err());
+
+        let failed: QueryResult = "{\"results\":[{\"statement_id\":0,\"error\":\"database not found: test\"}]}".parse().unwrap();
+        assert_eq!(Err("database not found: test".to_string()), failed.scalar::<u64>());
+    }
+
+    #[test]
+    fn test_query_result_statement_error() {
+        let result: QueryResult = "{\"results\":[{\"statement_id\":0,\"error\":\"database not found: test\"}]}".parse().unwrap();
+
+        assert_eq!(Some(0), result.results[0].statement_id);
+        assert_eq!(Some("database not found: test".to_string()), result.results[0].error);
+        assert!(result.results[0].series.is_empty());
+    }
+
+    #[test]
+    fn test_json_parser() {
+        let fixture = "{\"results\":[{\"series\":[{\"name\":\"sut\",\"columns\":[\"time\",\"value\"],\"values\":[[\"2015-06-11T20:46:02Z\",0.5]]}]}]}";
+
+        assert_eq!(fixture.parse::<QueryResult>(), JsonParser.parse(fixture));
+        assert!(JsonParser.parse("<html>").is_err());
+    }
+
+    #[test]
+    fn test_query_result_malformed() {
+        assert!("{\"results\":[{\"series\":{}}]}".parse::<QueryResult>().is_err());
+        assert!("<html>".parse::<QueryResult>().is_err());
+    }
+}
@@ -0,0 +1,121 @@
+use ::measurement::Measurement;
+use ::serializer::Serializer;
+
+/// `Measurement` with cached serialized line.
+///
+/// Useful for points written repeatedly (like heartbeats), where only the timestamp changes:
+/// the line is serialized once, and then just its trailing timestamp is replaced.
+pub struct CachedMeasurement<'a> {
+    measurement: Measurement<'a>,
+    // serialized line and length of its part without timestamp
+    cache: Option<(String, usize)>
+}
+
+impl<'a> CachedMeasurement<'a> {
+    /// Constructs a new `CachedMeasurement`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use influent::serializer::line::LineSerializer;
+    /// use influent::serializer::cached::CachedMeasurement;
+    /// use influent::measurement::{Measurement, Value};
+    ///
+    /// let serializer = LineSerializer::new();
+    /// let mut measurement = Measurement::new("heartbeat");
+    /// measurement.add_field("alive", Value::Boolean(true));
+    ///
+    /// let mut cached = CachedMeasurement::new(measurement);
+    ///
+    /// cached.set_timestamp(1);
+    /// assert_eq!("heartbeat alive=t 1", cached.serialize(&serializer));
+    ///
+    /// cached.set_timestamp(2);
+    /// assert_eq!("heartbeat alive=t 2", cached.serialize(&serializer));
+    /// ```
+    pub fn new(measurement: Measurement<'a>) -> CachedMeasurement<'a> {
+        CachedMeasurement {
+            measurement: measurement,
+            cache: None
+        }
+    }
+
+    /// Returns the measurement.
+    pub fn measurement(&self) -> &Measurement<'a> {
+        &self.measurement
+    }
+
+    /// Returns the measurement for mutation. Invalidates the cached line.
+    pub fn measurement_mut(&mut self) -> &mut Measurement<'a> {
+        self.cache = None;
+        &mut self.measurement
+    }
+
+    /// Sets the timestamp of the measurement, replacing only the timestamp of the cached line.
+    pub fn set_timestamp(&mut self, timestamp: i64) {
+        self.measurement.set_timestamp(timestamp);
+
+        match self.cache {
+            Some((ref mut line, len)) => {
+                line.truncate(len);
+                push_timestamp(line, timestamp);
+            }
+            _ => {}
+        }
+    }
+
+    /// Serializes the measurement, reusing cached line if it is valid.
+    ///
+    /// Note that cached line is reused regardless of the given serializer.
+    pub fn serialize(&mut self, serializer: &Serializer) -> &str {
+        if self.cache.is_none() {
+            let timestamp = self.measurement.timestamp.take();
+            let mut line = serializer.serialize(&self.measurement);
+            let len = line.len();
+
+            self.measurement.timestamp = timestamp;
+            match timestamp {
+                Some(t) => push_timestamp(&mut line, t),
+                _ => {}
+            }
+
+            self.cache = Some((line, len));
+        }
+
+        match self.cache {
+            Some((ref line, _)) => line,
+            None => unreachable!()
+        }
+    }
+}
+
+fn push_timestamp(line: &mut String, timestamp: i64) {
+    line.push(' ');
+    line.push_str(&timestamp.to_string());
+}
+
+#[cfg(test)]
+mod tests {
+    use super::CachedMeasurement;
+    use ::serializer::line::LineSerializer;
+    use ::measurement::{Measurement, Value};
+
+    #[test]
+    fn test_cached_measurement() {
+        let serializer = LineSerializer::new();
+        let mut measurement = Measurement::new("key");
+        measurement.add_field("value", Value::Integer(1));
+
+        let mut cached = CachedMeasurement::new(measurement);
+        assert_eq!("key value=1i", cached.serialize(&serializer));
+
+        cached.set_timestamp(10);
+        assert_eq!("key value=1i 10", cached.serialize(&serializer));
+
+        cached.set_timestamp(20);
+        assert_eq!("key value=1i 20", cached.serialize(&serializer));
+
+        cached.measurement_mut().add_tag("tag", "value");
+        assert_eq!("key,tag=value value=1i 20", cached.serialize(&serializer));
+    }
+}
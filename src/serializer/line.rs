@@ -1,7 +1,15 @@
-use ::measurement::{Measurement, Value};
-use ::serializer::Serializer;
-
-pub struct LineSerializer;
+use ::measurement::{Measurement, Value, NumericPolicy};
+use ::serializer::{Serializer, SerializeError, NoFieldsPolicy};
+use std::io::Write;
+use ::escaping::{line_escape_key_into, line_escape_tag_into, line_escape_field_value};
+
+pub struct LineSerializer {
+    float_decimal: bool,
+    float_precision: Option<usize>,
+    numeric_policy: NumericPolicy,
+    no_fields_policy: NoFieldsPolicy,
+    field_order: Vec<String>
+}
 
 /// Line spec `Measurement` serializer.
 impl LineSerializer {
@@ -23,75 +31,414 @@ impl LineSerializer {
     /// assert_eq!("key,tag=value field=\"value\"", serializer.serialize(&measurement));
     /// ```
     pub fn new() -> LineSerializer {
-        LineSerializer
+        LineSerializer {
+            float_decimal: false,
+            float_precision: None,
+            numeric_policy: NumericPolicy::Preserve,
+            no_fields_policy: NoFieldsPolicy::Fail,
+            field_order: vec![]
+        }
+    }
+
+    /// Sets whether whole floats are always rendered with decimal part, e.g. `1.0` instead of `1`,
+    /// so the value's float-ness is unambiguous on the wire. Disabled by default.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use influent::serializer::Serializer;
+    /// use influent::serializer::line::LineSerializer;
+    /// use influent::measurement::{Measurement, Value};
+    ///
+    /// let mut serializer = LineSerializer::new();
+    /// serializer.set_float_decimal(true);
+    ///
+    /// let mut measurement = Measurement::new("key");
+    /// measurement.add_field("field", Value::Float(1.0));
+    ///
+    /// assert_eq!("key field=1.0", serializer.serialize(&measurement));
+    /// ```
+    pub fn set_float_decimal(&mut self, always: bool) {
+        self.float_decimal = always;
+    }
+
+    /// Sets number of decimal places floats are rounded to, e.g. for reproducible output and
+    /// smaller payloads. By default floats are rendered in the shortest form, which parses back
+    /// to the same value.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use influent::serializer::Serializer;
+    /// use influent::serializer::line::LineSerializer;
+    /// use influent::measurement::{Measurement, Value};
+    ///
+    /// let mut serializer = LineSerializer::new();
+    /// serializer.set_float_precision(Some(2));
+    ///
+    /// let mut measurement = Measurement::new("key");
+    /// measurement.add_field("field", Value::Float(0.1 + 0.2));
+    ///
+    /// assert_eq!("key field=0.30", serializer.serialize(&measurement));
+    /// ```
+    pub fn set_float_precision(&mut self, precision: Option<usize>) {
+        self.float_precision = precision;
+    }
+
+    /// Sets numeric policy of measurements, which do not have their own. Defaults to
+    /// `NumericPolicy::Preserve`.
+    pub fn set_numeric_policy(&mut self, policy: NumericPolicy) {
+        self.numeric_policy = policy;
     }
-}
 
-fn escape(s: &str) -> String {
-    s
-        .replace(" ", "\\ ")
-        .replace(",", "\\,")
+    /// Sets whether `try_serialize`, `try_serialize_many` and `serialize_into` fail on measurement
+    /// without fields, or skip it. Defaults to `NoFieldsPolicy::Fail`, so writes of the clients,
+    /// which serialize with them, fail with `ClientError::Serialize`. Lossy `serialize` and
+    /// `serialize_many` always skip such measurements, including ones left without fields
+    /// after NaN and infinite floats are skipped.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use influent::serializer::{Serializer, NoFieldsPolicy};
+    /// use influent::serializer::line::LineSerializer;
+    /// use influent::measurement::Measurement;
+    ///
+    /// let mut serializer = LineSerializer::new();
+    /// assert!(serializer.try_serialize(&Measurement::new("key")).is_err());
+    ///
+    /// serializer.set_no_fields_policy(NoFieldsPolicy::Skip);
+    /// assert_eq!("", serializer.try_serialize(&Measurement::new("key")).unwrap());
+    /// ```
+    pub fn set_no_fields_policy(&mut self, policy: NoFieldsPolicy) {
+        self.no_fields_policy = policy;
+    }
+
+    /// Sets fields, which are serialized first in the given order, e.g. to match a fixed schema
+    /// of the reference system. Other fields follow in alphabetical order. Repeated fields keep
+    /// their first position.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use influent::serializer::Serializer;
+    /// use influent::serializer::line::LineSerializer;
+    /// use influent::measurement::{Measurement, Value};
+    ///
+    /// let mut serializer = LineSerializer::new();
+    /// serializer.set_field_order(vec!["value".to_string()]);
+    ///
+    /// let mut measurement = Measurement::new("key");
+    /// measurement.add_field("unit", Value::String("C"));
+    /// measurement.add_field("value", Value::Float(36.6));
+    ///
+    /// assert_eq!("key value=36.6,unit=\"C\"", serializer.serialize(&measurement));
+    /// ```
+    pub fn set_field_order(&mut self, fields: Vec<String>) {
+        self.field_order.clear();
+
+        for field in fields {
+            if !self.field_order.contains(&field) {
+                self.field_order.push(field);
+            }
+        }
+    }
 }
 
 fn as_string(s: &str) -> String {
-    format!("\"{}\"", s.replace("\"", "\\\""))
+    format!("\"{}\"", line_escape_field_value(s))
 }
 
 fn as_integer(i: &i64) -> String {
     format!("{}i", i)
 }
 
+fn as_unsigned(u: &u64) -> String {
+    format!("{}u", u)
+}
+
 fn as_float(f: &f64) -> String {
     f.to_string()
 }
 
+fn as_float_decimal(f: &f64) -> String {
+    if f.is_finite() && f.fract() == 0.0 {
+        format!("{}.0", f)
+    } else {
+        as_float(f)
+    }
+}
+
+fn as_float_fixed(f: &f64, precision: usize, decimal: bool) -> String {
+    if decimal && precision == 0 {
+        format!("{:.0}.0", f)
+    } else {
+        format!("{:.*}", precision, f)
+    }
+}
+
+// Heuristic length of the line, so that most lines fit without reallocations: names and
+// strings are counted without escaping, numbers and timestamps by their widest form, but
+// floats by a flat 24, which is exceeded e.g. by large values with fixed precision.
+fn estimated_len(measurement: &Measurement) -> usize {
+    let mut len = measurement.key.len();
+
+    for (tag, value) in measurement.tags.iter() {
+        len += tag.len() + value.len() + 2;
+    }
+
+    for (field, value) in measurement.fields.iter() {
+        len += field.len() + 2 + match *value {
+            Value::String(s)          => s.len() + 2,
+            Value::Float(_)           => 24,
+            Value::Integer(_)         => 21,
+            Value::UnsignedInteger(_) => 21,
+            Value::Boolean(_)         => 1
+        };
+    }
+
+    match measurement.timestamp {
+        Some(_) => len + 21,
+        None => len
+    }
+}
+
+// Returns the first name, which has newline: line protocol has no escaping for it.
+fn newline_name<'a>(measurement: &'a Measurement) -> Option<&'a str> {
+    Some(&measurement.key[..]).into_iter()
+        .chain(measurement.tags.keys().map(|tag| *tag))
+        .chain(measurement.tags.values().map(|value| *value))
+        .chain(measurement.fields.keys().map(|field| *field))
+        .find(|name| name.contains('\n'))
+}
+
 fn as_boolean(b: &bool) -> String {
     if *b { "t".to_string() } else { "f".to_string() }
 }
 
-impl Serializer for LineSerializer {
-    fn serialize(&self, measurement: &Measurement) -> String {
-        let mut line = vec![escape(measurement.key)];
+impl LineSerializer {
+    fn line(&self, measurement: &Measurement, strict: bool, omit_timestamp: bool) -> Result<String, SerializeError> {
+        let mut line = String::with_capacity(estimated_len(measurement));
+        try!(self.line_into(measurement, strict, omit_timestamp, &mut line));
+        Ok(line)
+    }
+
+    // Appends line to the buffer, returning whether it was appended: in lenient mode measurement
+    // left without fields is dropped. On error the buffer may have a partial line.
+    fn line_into(&self, measurement: &Measurement, strict: bool, omit_timestamp: bool, line: &mut String) -> Result<bool, SerializeError> {
+        // newline would split the line, corrupting the rest of the batch
+        match newline_name(measurement) {
+            Some(name) if strict => return Err(SerializeError::NewlineInName { key: measurement.key.to_string(), name: name.to_string() }),
+            Some(_) => return Ok(false),
+            None => {}
+        };
+
+        let start = line.len();
+
+        line_escape_key_into(&measurement.key, line);
 
         for (tag, value) in measurement.tags.iter() {
-            line.push(",".to_string());
-            line.push(escape(tag));
-            line.push("=".to_string());
-            line.push(escape(value));
+            line.push(',');
+            line_escape_tag_into(tag, line);
+            line.push('=');
+            line_escape_tag_into(value, line);
         }
 
+        let always_float = measurement.numeric_policy.unwrap_or(self.numeric_policy) == NumericPolicy::AlwaysFloat;
         let mut was_spaced = false;
 
-        for (field, value) in measurement.fields.iter() {
-            line.push({if !was_spaced { was_spaced = true; " " } else { "," }}.to_string());
-            line.push(escape(field));
-            line.push("=".to_string());
+        // serializer's order goes first, then insertion order of the measurement, if preserved
+        let mut order: Vec<&str> = self.field_order.iter().map(|field| &field[..]).collect();
+        match measurement.field_order {
+            Some(ref inserted) => order.extend(inserted.iter().filter(|field| !self.field_order.iter().any(|f| f == *field))),
+            None => {}
+        }
+
+        let ordered = order.iter()
+            .filter_map(|field| measurement.fields.get_key_value(field))
+            .map(|(field, value)| (*field, value));
+
+        let rest = measurement.fields.iter()
+            .filter(|&(field, _)| !order.contains(field))
+            .map(|(field, value)| (*field, value));
 
+        for (field, value) in ordered.chain(rest) {
+            // NaN and infinities are invalid in line protocol
             match value {
-                &Value::String(ref s)  => line.push(as_string(s)),
-                &Value::Integer(ref i) => line.push(as_integer(i)),
-                &Value::Float(ref f)   => line.push(as_float(f)),
-                &Value::Boolean(ref b) => line.push(as_boolean(b))
+                &Value::Float(ref f) if !f.is_finite() => {
+                    if strict {
+                        return Err(SerializeError::NonFiniteFloat { key: measurement.key.to_string(), field: field.to_string() });
+                    }
+                    continue;
+                },
+                _ => {}
+            };
+
+            line.push(if !was_spaced { was_spaced = true; ' ' } else { ',' });
+            line_escape_tag_into(field, line);
+            line.push('=');
+
+            match (value, self.float_precision) {
+                (&Value::String(ref s), _)  => line.push_str(&as_string(s)),
+                (&Value::Integer(ref i), _) if always_float => line.push_str(&as_float_decimal(&(*i as f64))),
+                (&Value::Integer(ref i), _) => line.push_str(&as_integer(i)),
+                (&Value::UnsignedInteger(ref u), _) if always_float => line.push_str(&as_float_decimal(&(*u as f64))),
+                (&Value::UnsignedInteger(ref u), _) => line.push_str(&as_unsigned(u)),
+                (&Value::Float(ref f), Some(precision)) => line.push_str(&as_float_fixed(f, precision, always_float || self.float_decimal)),
+                (&Value::Float(ref f), None) if always_float || self.float_decimal => line.push_str(&as_float_decimal(f)),
+                (&Value::Float(ref f), None) => line.push_str(&as_float(f)),
+                (&Value::Boolean(ref b), _) => line.push_str(&as_boolean(b))
             };
         }
 
+        // line without fields is rejected by InfluxDB, failing the whole batch
+        if !was_spaced {
+            if strict && self.no_fields_policy == NoFieldsPolicy::Fail {
+                return Err(SerializeError::NoFields { key: measurement.key.to_string() });
+            }
+
+            line.truncate(start);
+            return Ok(false);
+        }
+
         match measurement.timestamp {
-            Some(t) => {
-                line.push(" ".to_string());
-                line.push(t.to_string());
+            Some(t) if !omit_timestamp => {
+                line.push(' ');
+                line.push_str(&t.to_string());
             }
             _ => {}
         }
 
-        line.connect("")
+        Ok(true)
+    }
+}
+
+impl Serializer for LineSerializer {
+    fn serialize(&self, measurement: &Measurement) -> String {
+        match self.line(measurement, false, false) {
+            Ok(line) => line,
+            Err(_) => unreachable!("lenient serialization does not fail")
+        }
+    }
+
+    fn try_serialize(&self, measurement: &Measurement) -> Result<String, SerializeError> {
+        self.line(measurement, true, false)
+    }
+
+    fn try_serialize_without_timestamp(&self, measurement: &Measurement) -> Result<String, SerializeError> {
+        self.line(measurement, true, true)
+    }
+
+    /// Lines are appended to the single buffer, separated by newlines.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use influent::serializer::Serializer;
+    /// use influent::serializer::line::LineSerializer;
+    /// use influent::measurement::Measurement;
+    ///
+    /// let measurements = vec![Measurement::new("a").with_field("f", 1i64), Measurement::new("b").with_field("f", 2i64)];
+    ///
+    /// assert_eq!("a f=1i\nb f=2i", LineSerializer::new().serialize_many(&measurements));
+    /// ```
+    fn serialize_many(&self, measurements: &[Measurement]) -> String {
+        let capacity = measurements.iter().fold(0, |len, measurement| len + estimated_len(measurement) + 1);
+        let mut buf = String::with_capacity(capacity);
+
+        for measurement in measurements {
+            let start = buf.len();
+
+            if start > 0 {
+                buf.push('\n');
+            }
+
+            match self.line_into(measurement, false, false, &mut buf) {
+                Ok(true) => {},
+                Ok(false) => buf.truncate(start),
+                Err(_) => unreachable!("lenient serialization does not fail")
+            }
+        }
+
+        buf
+    }
+
+    /// Lines are appended to the single buffer, separated by newlines, failing as `try_serialize`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use influent::serializer::{Serializer, NoFieldsPolicy};
+    /// use influent::serializer::line::LineSerializer;
+    /// use influent::measurement::Measurement;
+    ///
+    /// let mut serializer = LineSerializer::new();
+    /// let measurements = vec![Measurement::new("a").with_field("f", 1i64), Measurement::new("b"), Measurement::new("c").with_field("f", 2i64)];
+    ///
+    /// assert_eq!(1, serializer.try_serialize_many(&measurements).unwrap_err().0);
+    ///
+    /// serializer.set_no_fields_policy(NoFieldsPolicy::Skip);
+    /// assert_eq!(("a f=1i\nc f=2i".to_string(), 2), serializer.try_serialize_many(&measurements).unwrap());
+    /// ```
+    fn try_serialize_many(&self, measurements: &[Measurement]) -> Result<(String, usize), (usize, SerializeError)> {
+        let capacity = measurements.iter().fold(0, |len, measurement| len + estimated_len(measurement) + 1);
+        let mut buf = String::with_capacity(capacity);
+        let mut count = 0;
+
+        for (i, measurement) in measurements.iter().enumerate() {
+            let start = buf.len();
+
+            if start > 0 {
+                buf.push('\n');
+            }
+
+            match self.line_into(measurement, true, false, &mut buf) {
+                Ok(true) => count += 1,
+                Ok(false) => buf.truncate(start),
+                Err(err) => return Err((i, err))
+            }
+        }
+
+        Ok((buf, count))
+    }
+
+    /// Unlike `serialize`, which skips NaN and infinite floats and drops measurement left without
+    /// fields or having newline in a name, fails on such field, naming it and the measurement,
+    /// on measurement without fields, or on name with newline.
+    ///
+    /// Line is built in memory and written at once, so unbuffered writer gets a single write.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use influent::serializer::Serializer;
+    /// use influent::serializer::line::LineSerializer;
+    /// use influent::measurement::{Measurement, Value};
+    ///
+    /// let serializer = LineSerializer::new();
+    /// let mut measurement = Measurement::new("key");
+    /// measurement.add_field("field", Value::Integer(1));
+    ///
+    /// let mut buf = Vec::new();
+    /// serializer.serialize_into(&measurement, &mut buf).unwrap();
+    ///
+    /// assert_eq!(b"key field=1i", &buf[..]);
+    /// ```
+    fn serialize_into(&self, measurement: &Measurement, w: &mut Write) -> Result<(), SerializeError> {
+        let line = try!(self.line(measurement, true, false));
+        try!(w.write_all(line.as_bytes()));
+        Ok(())
     }
 }
 
 #[cfg(test)]
 mod tests {
-    use super::{as_boolean, as_string, as_integer, as_float, escape, LineSerializer};
+    use ::serializer::{SerializeError, NoFieldsPolicy};
+    use super::{as_boolean, as_string, as_integer, as_unsigned, as_float, as_float_decimal, as_float_fixed, estimated_len, LineSerializer};
     use ::serializer::Serializer;
-    use ::measurement::{Measurement, Value};
+    use ::measurement::{Measurement, Value, NumericPolicy};
+    use std::io::{self, Write};
+    use std::f64;
 
     #[test]
     fn test_as_boolean() {
@@ -112,6 +459,13 @@ mod tests {
         assert_eq!("-10i",  as_integer(&-10i64));
     }
 
+    #[test]
+    fn test_as_unsigned() {
+        assert_eq!("0u", as_unsigned(&0u64));
+        assert_eq!("1u", as_unsigned(&1u64));
+        assert_eq!("18446744073709551615u", as_unsigned(&u64::max_value()));
+    }
+
     #[test]
     fn test_as_float() {
         assert_eq!("1", as_float(&1f64));
@@ -121,10 +475,330 @@ mod tests {
     }
 
     #[test]
-    fn test_escape() {
-        assert_eq!("\\ ", escape(" "));
-        assert_eq!("\\,", escape(","));
-        assert_eq!("hello\\,\\ gobwas", escape("hello, gobwas"));
+    fn test_as_float_decimal() {
+        assert_eq!("1.0", as_float_decimal(&1f64));
+        assert_eq!("-10.0", as_float_decimal(&-10f64));
+        assert_eq!("0.0", as_float_decimal(&0f64));
+        assert_eq!("-3.14", as_float_decimal(&-3.14f64));
+    }
+
+    #[test]
+    fn test_estimated_len() {
+        let serializer = LineSerializer::new();
+
+        let measurements = vec![
+            Measurement::new("key"),
+            Measurement::new("key").with_tag("tag", "value").with_field("s", "string").with_timestamp(-1434055562000000000),
+            Measurement::new("key").with_field("f", -3.141592653589793).with_field("i", i64::min_value()).with_field("u", u64::max_value()).with_field("b", true)
+        ];
+
+        for measurement in measurements.iter() {
+            let line = serializer.line(measurement, false, false).unwrap();
+            assert!(estimated_len(measurement) >= line.len(), "line: {}", line);
+            assert_eq!(estimated_len(measurement), line.capacity());
+        }
+
+        assert_eq!("key,tag=value s=\"string\" -1434055562000000000", serializer.serialize_many(&measurements[1..2]));
+    }
+
+    #[test]
+    fn test_line_serializer_float_decimal() {
+        let mut serializer = LineSerializer::new();
+        let mut measurement = Measurement::new("key");
+
+        measurement.add_field("f", Value::Float(1.0));
+        assert_eq!("key f=1", serializer.serialize(&measurement));
+
+        serializer.set_float_decimal(true);
+        assert_eq!("key f=1.0", serializer.serialize(&measurement));
+    }
+
+    #[test]
+    fn test_as_float_fixed() {
+        assert_eq!("0.30", as_float_fixed(&(0.1f64 + 0.2f64), 2, false));
+        assert_eq!("1.000", as_float_fixed(&1f64, 3, false));
+        assert_eq!("-3", as_float_fixed(&-3.14f64, 0, false));
+        assert_eq!("-3.0", as_float_fixed(&-3.14f64, 0, true));
+    }
+
+    #[test]
+    fn test_line_serializer_float_precision() {
+        let mut serializer = LineSerializer::new();
+        let mut measurement = Measurement::new("key");
+
+        measurement.add_field("f", Value::Float(0.1 + 0.2));
+        measurement.add_field("g", Value::Float(2.0));
+        measurement.add_field("i", Value::Integer(1));
+        assert_eq!("key f=0.30000000000000004,g=2,i=1i", serializer.serialize(&measurement));
+
+        serializer.set_float_precision(Some(3));
+        assert_eq!("key f=0.300,g=2.000,i=1i", serializer.serialize(&measurement));
+
+        serializer.set_float_precision(Some(0));
+        assert_eq!("key f=0,g=2,i=1i", serializer.serialize(&measurement));
+
+        serializer.set_float_decimal(true);
+        assert_eq!("key f=0.0,g=2.0,i=1i", serializer.serialize(&measurement));
+    }
+
+    #[test]
+    fn test_line_serializer_numeric_policy() {
+        let mut serializer = LineSerializer::new();
+        serializer.set_numeric_policy(NumericPolicy::AlwaysFloat);
+
+        let mut counter = Measurement::new("counter").with_numeric_policy(NumericPolicy::Preserve);
+        counter.add_field("i", Value::Integer(10));
+        counter.add_field("f", Value::Float(1.0));
+
+        let mut gauge = Measurement::new("gauge");
+        gauge.add_field("i", Value::Integer(10));
+        gauge.add_field("f", Value::Float(1.0));
+
+        assert_eq!("counter f=1,i=10i", serializer.serialize(&counter));
+        assert_eq!("gauge f=1.0,i=10.0", serializer.serialize(&gauge));
+    }
+
+    #[test]
+    fn test_line_serializer_field_order() {
+        let mut serializer = LineSerializer::new();
+        serializer.set_field_order(vec!["z".to_string(), "missing".to_string(), "m".to_string()]);
+
+        let mut measurement = Measurement::new("key");
+        measurement.add_field("a", Value::Integer(1));
+        measurement.add_field("m", Value::Integer(2));
+        measurement.add_field("b", Value::Integer(3));
+        measurement.add_field("z", Value::Integer(4));
+
+        assert_eq!("key z=4i,m=2i,a=1i,b=3i", serializer.serialize(&measurement));
+    }
+
+    #[test]
+    fn test_line_serializer_field_order_repeated() {
+        let mut serializer = LineSerializer::new();
+        serializer.set_field_order(vec!["m".to_string(), "a".to_string(), "m".to_string()]);
+
+        let mut measurement = Measurement::new("key");
+        measurement.add_field("a", Value::Integer(1));
+        measurement.add_field("m", Value::Integer(2));
+
+        assert_eq!("key m=2i,a=1i", serializer.serialize(&measurement));
+        assert_eq!("key m=2i,a=1i", serializer.try_serialize(&measurement).unwrap());
+    }
+
+    struct FailingWriter;
+
+    impl Write for FailingWriter {
+        fn write(&mut self, _: &[u8]) -> io::Result<usize> {
+            Err(io::Error::new(io::ErrorKind::Other, "disk is full"))
+        }
+
+        fn flush(&mut self) -> io::Result<()> {
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn test_line_serializer_serialize_into() {
+        let serializer = LineSerializer::new();
+        let mut measurement = Measurement::new("key");
+        measurement.add_field("f", Value::Integer(1));
+
+        let mut buf = Vec::new();
+        serializer.serialize_into(&measurement, &mut buf).unwrap();
+        serializer.serialize_into(&measurement, &mut buf).unwrap();
+        assert_eq!(b"key f=1ikey f=1i", &buf[..]);
+
+        match serializer.serialize_into(&measurement, &mut FailingWriter) {
+            Err(SerializeError::Io(err)) => assert_eq!("disk is full", err.to_string()),
+            other => panic!("unexpected result: {:?}", other)
+        }
+    }
+
+    #[test]
+    fn test_line_serializer_insertion_order() {
+        let mut measurement = Measurement::ordered("key")
+            .with_tag("z", "1")
+            .with_tag("a", "2")
+            .with_field("z", 1i64)
+            .with_field("m", 2i64)
+            .with_field("a", 3i64)
+            .with_field("m", 4i64);
+
+        let mut serializer = LineSerializer::new();
+        assert_eq!("key,a=2,z=1 z=1i,m=4i,a=3i", serializer.serialize(&measurement));
+
+        measurement.remove_field("z");
+        measurement.fields.insert("b", Value::Integer(5));
+        serializer.set_field_order(vec!["a".to_string()]);
+
+        assert_eq!("key,a=2,z=1 a=3i,m=4i,b=5i", serializer.serialize(&measurement));
+    }
+
+    #[test]
+    fn test_line_serializer_serialize_many() {
+        let serializer = LineSerializer::new();
+
+        let measurements = vec![
+            Measurement::new("a").with_tag("host", "x").with_field("f", 1i64).with_timestamp(10),
+            Measurement::new("b").with_field("f", f64::NAN).with_field("s", "ok")
+        ];
+
+        assert_eq!("a,host=x f=1i 10\nb s=\"ok\"", serializer.serialize_many(&measurements));
+        assert_eq!("", serializer.serialize_many(&[]));
+    }
+
+    #[test]
+    fn test_line_serializer_serialize_into_many() {
+        let serializer = LineSerializer::new();
+        let mut buf = Vec::new();
+
+        for (i, key) in vec!["a", "b", "c"].into_iter().enumerate() {
+            if i > 0 {
+                buf.push(b'\n');
+            }
+
+            let measurement = Measurement::new(key).with_field("f", Value::Integer(i as i64));
+            serializer.serialize_into(&measurement, &mut buf).unwrap();
+        }
+
+        assert_eq!("a f=0i\nb f=1i\nc f=2i", String::from_utf8(buf).unwrap());
+    }
+
+    #[test]
+    fn test_line_serializer_without_timestamp() {
+        let serializer = LineSerializer::new();
+
+        // timestamp is omitted, even if the line ends with the same text
+        let measurement = Measurement::new("key").with_field("s", "a 10").with_timestamp(10);
+
+        assert_eq!("key s=\"a 10\"", serializer.try_serialize_without_timestamp(&measurement).unwrap());
+        assert_eq!("key s=\"a 10\" 10", serializer.try_serialize(&measurement).unwrap());
+        assert_eq!("key i=10i", serializer.try_serialize_without_timestamp(&Measurement::new("key").with_field("i", 10i64)).unwrap());
+
+        match serializer.try_serialize_without_timestamp(&Measurement::new("key").with_timestamp(10)) {
+            Err(SerializeError::NoFields { .. }) => {},
+            other => panic!("unexpected result: {:?}", other)
+        }
+    }
+
+    #[test]
+    fn test_line_serializer_newline_in_name() {
+        let serializer = LineSerializer::new();
+
+        let measurements = vec![
+            Measurement::new("a\nb").with_field("f", 1i64),
+            Measurement::new("key").with_tag("host", "server\n01").with_field("f", 1i64),
+            Measurement::new("key").with_field("f\n", 1i64)
+        ];
+
+        for (measurement, name) in measurements.iter().zip(vec!["a\nb", "server\n01", "f\n"]) {
+            assert_eq!("", serializer.serialize(measurement));
+
+            match serializer.try_serialize(measurement) {
+                Err(SerializeError::NewlineInName { name: ref invalid, .. }) => assert_eq!(name, invalid),
+                other => panic!("unexpected result: {:?}", other)
+            }
+        }
+
+        let batch = vec![Measurement::new("a").with_field("f", 1i64), Measurement::new("b\nc").with_field("f", 2i64)];
+        assert_eq!("a f=1i", serializer.serialize_many(&batch));
+
+        // newline in string field value is quoted, so it is kept
+        assert_eq!("key f=\"a\nb\"", serializer.serialize(&Measurement::new("key").with_field("f", "a\nb")));
+    }
+
+    #[test]
+    fn test_line_serializer_non_finite_float() {
+        let serializer = LineSerializer::new();
+
+        for f in &[f64::NAN, f64::INFINITY, f64::NEG_INFINITY] {
+            let mut measurement = Measurement::new("key");
+            measurement.add_field("a", Value::Integer(1));
+            measurement.add_field("b", Value::Float(*f));
+            measurement.add_field("c", Value::Float(0.5));
+
+            assert_eq!("key a=1i,c=0.5", serializer.serialize(&measurement));
+
+            match serializer.serialize_into(&measurement, &mut Vec::new()) {
+                Err(SerializeError::NonFiniteFloat { key, field }) => {
+                    assert_eq!("key", key);
+                    assert_eq!("b", field);
+                },
+                other => panic!("unexpected result for {}: {:?}", f, other)
+            }
+        }
+    }
+
+    #[test]
+    fn test_line_serializer_no_fields_policy() {
+        let mut serializer = LineSerializer::new();
+        let measurement = Measurement::new("key").with_tag("tag", "value").with_timestamp(10);
+
+        match serializer.try_serialize(&measurement) {
+            Err(SerializeError::NoFields { key }) => assert_eq!("key", key),
+            other => panic!("unexpected result: {:?}", other)
+        }
+
+        serializer.set_no_fields_policy(NoFieldsPolicy::Skip);
+        assert_eq!("", serializer.try_serialize(&measurement).unwrap());
+
+        let mut buf = Vec::new();
+        serializer.serialize_into(&measurement, &mut buf).unwrap();
+        assert!(buf.is_empty());
+
+        // NaN still fails the strict serialization
+        assert!(serializer.try_serialize(&Measurement::new("key").with_field("f", f64::NAN)).is_err());
+    }
+
+    #[test]
+    fn test_line_serializer_all_non_finite() {
+        let serializer = LineSerializer::new();
+
+        let mut measurement = Measurement::new("key");
+        measurement.add_tag("tag", "value");
+        measurement.add_field("a", Value::Float(f64::NAN));
+        measurement.add_field("b", Value::Float(f64::INFINITY));
+        measurement.set_timestamp(10);
+
+        assert_eq!("", serializer.serialize(&measurement));
+
+        let batch = vec![
+            Measurement::new("a").with_field("f", f64::NAN),
+            Measurement::new("b").with_field("f", 1i64),
+            Measurement::new("c").with_field("f", f64::NEG_INFINITY),
+            Measurement::new("d").with_field("f", 2i64),
+            Measurement::new("e")
+        ];
+        assert_eq!("b f=1i\nd f=2i", serializer.serialize_many(&batch));
+
+        match serializer.try_serialize(&Measurement::new("empty")) {
+            Err(SerializeError::NoFields { key }) => assert_eq!("empty", key),
+            other => panic!("unexpected result: {:?}", other)
+        }
+    }
+
+    #[test]
+    fn test_line_serializer_serialize_into_error_context() {
+        let serializer = LineSerializer::new();
+        let mut batch = vec![];
+
+        for key in &["cpu", "mem", "disk"] {
+            let mut measurement = Measurement::new(*key);
+            measurement.add_field("used", Value::Float(0.5));
+            batch.push(measurement);
+        }
+        batch[1].add_field("ratio", Value::Float(0.0 / 0.0));
+
+        let mut buf = Vec::new();
+        let err = batch.iter().map(|m| serializer.serialize_into(m, &mut buf)).find(|r| r.is_err());
+
+        match err {
+            Some(Err(SerializeError::NonFiniteFloat { key, field })) => {
+                assert_eq!("mem", key);
+                assert_eq!("ratio", field);
+            },
+            other => panic!("unexpected result: {:?}", other)
+        }
     }
 
     #[test]
@@ -159,6 +833,20 @@ mod tests {
 
         assert_eq!("key s=\"string\" 1434055562000000000", serializer.serialize(&measurement));
     }
+
+    #[test]
+    fn test_line_serializer_timestamp_opt() {
+        let serializer = LineSerializer::new();
+        let mut measurement = Measurement::new("key");
+
+        measurement.add_field("s", Value::String("string"));
+
+        measurement.set_timestamp_opt(Some(10));
+        assert_eq!("key s=\"string\" 10", serializer.serialize(&measurement));
+
+        measurement.set_timestamp_opt(None);
+        assert_eq!("key s=\"string\"", serializer.serialize(&measurement));
+    }
 }
 
 
@@ -1,9 +1,149 @@
 use ::measurement::Measurement;
+use std::io;
+use std::io::Write;
 
 pub mod line;
+pub mod cached;
+
+/// Error of the measurement serialization.
+#[derive(Debug)]
+pub enum SerializeError {
+    /// Writer failed.
+    Io(io::Error),
+    /// Float field is NaN or infinite, which line protocol can not represent.
+    NonFiniteFloat {
+        /// Measurement key.
+        key: String,
+        /// Field name.
+        field: String
+    },
+    /// Measurement has no fields, e.g. all of them are NaN, so its line would be rejected.
+    NoFields {
+        /// Measurement key.
+        key: String
+    },
+    /// Measurement name, tag or field name contains newline, which line protocol can not escape.
+    NewlineInName {
+        /// Measurement key.
+        key: String,
+        /// Name with the newline.
+        name: String
+    }
+}
+
+/// Handling of measurement without fields by fallible serialization, as line protocol
+/// requires at least one field.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum NoFieldsPolicy {
+    /// Fail with `SerializeError::NoFields`.
+    Fail,
+    /// Skip the measurement, producing no output for it.
+    Skip
+}
+
+impl From<io::Error> for SerializeError {
+    fn from(e: io::Error) -> Self {
+        SerializeError::Io(e)
+    }
+}
 
 /// `Measurement` serializer.
 pub trait Serializer {
-    /// Serializes measurement to String.
-    fn serialize(&self, measurement: &Measurement) -> String;
-}
\ No newline at end of file
+    /// Serializes measurement to the writer, e.g. to write large batches without building
+    /// them in memory, failing on the value, which could not be represented.
+    fn serialize_into(&self, measurement: &Measurement, w: &mut Write) -> Result<(), SerializeError>;
+
+    /// Serializes measurement to String. By default the `serialize_into` output is collected.
+    ///
+    /// Serialization is lossy: measurement, which could not be serialized, is dropped as
+    /// an empty string, and the error is logged. Use `try_serialize` to handle the error.
+    fn serialize(&self, measurement: &Measurement) -> String {
+        match self.try_serialize(measurement) {
+            Ok(line) => line,
+            Err(err) => {
+                warn!("could not serialize {:?}: {:?}", measurement.key, err);
+                String::new()
+            }
+        }
+    }
+
+    /// Serializes measurement, failing on the value, which could not be represented.
+    /// By default the `serialize_into` output is collected.
+    fn try_serialize(&self, measurement: &Measurement) -> Result<String, SerializeError> {
+        let mut buf = Vec::new();
+        try!(self.serialize_into(measurement, &mut buf));
+
+        String::from_utf8(buf).map_err(|e| SerializeError::Io(io::Error::new(io::ErrorKind::InvalidData, e)))
+    }
+
+    /// Serializes measurement as if it had no timestamp, so the server assigns its own,
+    /// failing as `try_serialize` does.
+    fn try_serialize_without_timestamp(&self, measurement: &Measurement) -> Result<String, SerializeError>;
+
+    /// Serializes measurements to the body of the write request. By default measurements are
+    /// serialized one per line with lossy `serialize`.
+    fn serialize_many(&self, measurements: &[Measurement]) -> String {
+        let lines: Vec<String> = measurements.iter().map(|m| self.serialize(m)).collect();
+        lines.join("\n")
+    }
+
+    /// Serializes measurements to the body of the write request, failing with the index of
+    /// the first measurement, which could not be serialized. Returns the body along with
+    /// the count of measurements in it, as those skipped by the serializer, e.g. without fields
+    /// under `NoFieldsPolicy::Skip`, are left out.
+    ///
+    /// By default measurements are serialized one per line with `try_serialize`; formats
+    /// encoding the whole batch at once override this.
+    fn try_serialize_many(&self, measurements: &[Measurement]) -> Result<(String, usize), (usize, SerializeError)> {
+        let mut lines = vec![];
+
+        for (i, measurement) in measurements.iter().enumerate() {
+            match self.try_serialize(measurement) {
+                Ok(ref line) if line.is_empty() => {},
+                Ok(line) => lines.push(line),
+                Err(err) => return Err((i, err))
+            }
+        }
+
+        Ok((lines.join("\n"), lines.len()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Serializer, SerializeError};
+    use ::measurement::Measurement;
+    use std::io::Write;
+
+    // Writes measurement key only, failing on empty one.
+    struct KeySerializer;
+
+    impl Serializer for KeySerializer {
+        fn serialize_into(&self, measurement: &Measurement, w: &mut Write) -> Result<(), SerializeError> {
+            if measurement.key.is_empty() {
+                return Err(SerializeError::NoFields { key: String::new() });
+            }
+
+            try!(w.write_all(measurement.key.as_bytes()));
+            Ok(())
+        }
+
+        fn try_serialize_without_timestamp(&self, measurement: &Measurement) -> Result<String, SerializeError> {
+            self.try_serialize(measurement)
+        }
+    }
+
+    #[test]
+    fn test_serializer_defaults() {
+        let serializer = KeySerializer;
+
+        assert_eq!("a", serializer.serialize(&Measurement::new("a")));
+        assert_eq!("", serializer.serialize(&Measurement::new("")));
+        assert_eq!("b", serializer.try_serialize(&Measurement::new("b")).unwrap());
+        assert!(serializer.try_serialize(&Measurement::new("")).is_err());
+        assert_eq!("a\nb", serializer.serialize_many(&[Measurement::new("a"), Measurement::new("b")]));
+
+        assert_eq!(("a\nb".to_string(), 2), serializer.try_serialize_many(&[Measurement::new("a"), Measurement::new("b")]).unwrap());
+        assert_eq!(Some(1), serializer.try_serialize_many(&[Measurement::new("a"), Measurement::new("")]).err().map(|(i, _)| i));
+    }
+}
@@ -0,0 +1,76 @@
+use ::measurement::Measurement;
+use ::serializer::Serializer;
+use ::serializer::line::LineSerializer;
+
+// Describes the first difference of the lines, pointing at it with a caret.
+fn diff(expected: &str, actual: &str) -> String {
+    let at = expected.chars().zip(actual.chars())
+        .position(|(e, a)| e != a)
+        .unwrap_or(::std::cmp::min(expected.chars().count(), actual.chars().count()));
+
+    let mut message = format!("line protocol mismatch at {}:\nexpected: {}\n  actual: {}\n          {}^",
+                              at, expected, actual, " ".repeat(at));
+
+    let differs_in_escaping = expected.chars().nth(at) == Some('\\') || actual.chars().nth(at) == Some('\\');
+
+    if differs_in_escaping {
+        message.push_str(" (escaping differs)");
+    }
+
+    message
+}
+
+/// Serializes measurement with `LineSerializer` and asserts it equals to `expected`, panicking
+/// with the position of the first difference otherwise. Handy for golden tests of measurements.
+///
+/// # Examples
+///
+/// ```
+/// use influent::testing::assert_line;
+/// use influent::measurement::{Measurement, Value};
+///
+/// let mut measurement = Measurement::new("cpu");
+/// measurement.add_tag("host", "server 01");
+/// measurement.add_field("load", Value::Float(0.5));
+///
+/// assert_line(&measurement, "cpu,host=server\\ 01 load=0.5");
+/// ```
+pub fn assert_line(measurement: &Measurement, expected: &str) {
+    let actual = LineSerializer::new().serialize(measurement);
+
+    if actual != expected {
+        panic!("{}", diff(expected, &actual));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{assert_line, diff};
+    use ::measurement::{Measurement, Value};
+
+    #[test]
+    fn test_assert_line() {
+        let mut measurement = Measurement::new("key");
+        measurement.add_tag("tag", "a,b");
+        measurement.add_field("f", Value::Integer(1));
+        measurement.set_timestamp(10);
+
+        assert_line(&measurement, "key,tag=a\\,b f=1i 10");
+    }
+
+    #[test]
+    #[should_panic(expected = "escaping differs")]
+    fn test_assert_line_mismatch() {
+        let mut measurement = Measurement::new("key");
+        measurement.add_tag("tag", "a,b");
+        measurement.add_field("f", Value::Integer(1));
+
+        assert_line(&measurement, "key,tag=a,b f=1i");
+    }
+
+    #[test]
+    fn test_diff() {
+        assert_eq!("line protocol mismatch at 6:\nexpected: key f=2i\n  actual: key f=1i\n                ^", diff("key f=2i", "key f=1i"));
+        assert_eq!("line protocol mismatch at 3:\nexpected: key\n  actual: key 10\n             ^", diff("key", "key 10"));
+    }
+}
@@ -6,6 +6,7 @@ use influent::client::{Client, Credentials};
 use influent::client::http::HttpClient;
 use influent::hurl::hyper::HyperHurl;
 use influent::measurement::{Measurement, Value};
+use influent::escaping::influxql_quote_ident;
 
 fn before<'a>() -> HttpClient<'a> {
 	let credentials = Credentials {
@@ -43,4 +44,37 @@ fn test_write_measurement() {
 
     let fixture = "{\"results\":[{\"series\":[{\"name\":\"sut\",\"columns\":[\"time\",\"boolean\",\"float\",\"integer\",\"string\",\"tag\",\"tag, with comma\",\"with, comma\"],\"values\":[[\"2015-06-11T20:46:02Z\",false,10,10,\"string\",\"value\",\"three, four\",\"comma, with\"]]}]}]}";
     assert_eq!(fixture, client.query("select * from \"sut\"".to_string(), None).unwrap());
-}
\ No newline at end of file
+}
+
+#[test]
+fn test_write_many_single_request() {
+    let client = before();
+
+    let measurements: Vec<Measurement> = (0..100)
+        .map(|i| {
+            let mut measurement = Measurement::new("batch");
+            measurement.add_field("value", Value::Integer(i));
+            measurement.set_timestamp(1434055562000000000 + i);
+            measurement
+        })
+        .collect();
+
+    assert_eq!(100, client.write_many(&measurements, None).unwrap().written);
+
+    let fixture = "{\"results\":[{\"series\":[{\"name\":\"batch\",\"columns\":[\"time\",\"count\"],\"values\":[[\"1970-01-01T00:00:00Z\",100]]}]}]}";
+    assert_eq!(fixture, client.query("select count(value) from batch".to_string(), None).unwrap());
+}
+
+#[test]
+fn test_query_quoted_identifier() {
+    let client = before();
+
+    let mut measurement = Measurement::new("cpu \"load\"");
+    measurement.add_field("value", Value::Integer(1));
+    measurement.set_timestamp(1434055562000000000);
+
+    assert!(client.write_one(measurement, None).is_ok());
+
+    let fixture = "{\"results\":[{\"series\":[{\"name\":\"cpu \\\"load\\\"\",\"columns\":[\"time\",\"value\"],\"values\":[[\"2015-06-11T20:46:02Z\",1]]}]}]}";
+    assert_eq!(fixture, client.query(format!("select value from {}", influxql_quote_ident("cpu \"load\"")), None).unwrap());
+}